@@ -1,23 +1,37 @@
 #![doc = include_str!("../../README.md")]
 use std::io;
 use std::thread;
+use std::collections::{HashMap, VecDeque};
 use std::io::Read;
+use std::path::PathBuf;
 use std::sync::mpsc;
+use std::time::Duration;
 
 use tui::UI;
 use tui::Encoding;
 use tui::draw;
 use tui::draw::Colour::TrueColour;
-use parse;
-use proxy;
+use tui::input::{Key, KeyDecoder};
+use parse::diff::{diff_lines, Change};
+use parse::flow::Flow;
+use parse::http_edit;
+use parse::http_request::HTTPRequest;
+use parse::http_response::HTTPResponse;
+use proxy::Limits;
+use proxy::builder::{ProxyBuilder, ProxyHandle};
+use proxy::ca::CertificateAuthority;
+use proxy::event::ProxyEvent;
+use proxy::hold::{Direction, Held, Verdict};
 
-fn read_commands(sender: mpsc::Sender<char>) -> thread::JoinHandle<()> {
+fn read_commands(sender: mpsc::Sender<u8>) -> thread::JoinHandle<()> {
     thread::spawn(move || {
         let mut buf = [0u8; 1];
         loop {
             match io::stdin().read(&mut buf) {
+                // Stop reading when standard input is closed.
+                Ok(0) => break,
                 Ok(_) => {
-                    match sender.send(char::from(buf[0])) {
+                    match sender.send(buf[0]) {
                         Ok(_) => {},
                         Err(_) => {},
                     };
@@ -28,44 +42,375 @@ fn read_commands(sender: mpsc::Sender<char>) -> thread::JoinHandle<()> {
     })
 }
 
+// Values following every occurrence of a command line option, such as --listen <address>.
+fn option_values(name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            if let Some(value) = args.next() {
+                values.push(value);
+            }
+        }
+    }
+    return values;
+}
+
+// Value following a command line option, such as --ca-dir <directory>.
+fn option_value(name: &str) -> Option<String> {
+    option_values(name).into_iter().next()
+}
+
+// Limits given as --max-connections <count> and --timeout <seconds>, where a timeout of 0 waits
+// indefinitely.
+fn limits() -> Result<Limits, String> {
+    let mut limits = Limits::default();
+    if let Some(value) = option_value("--max-connections") {
+        limits.max_connections = value.parse().map_err(|_| format!("Invalid connection count: {value}"))?;
+    }
+    if let Some(value) = option_value("--timeout") {
+        let seconds: u64 = value.parse().map_err(|_| format!("Invalid timeout: {value}"))?;
+        limits.timeout = if seconds == 0 { None } else { Some(Duration::from_secs(seconds)) };
+    }
+    return Ok(limits);
+}
+
+// Give the verdict for the oldest held message.
+fn release(proxy: &ProxyHandle, held: &mut VecDeque<Held>, verdict: Verdict) {
+    if let Some(message) = held.pop_front() {
+        proxy.release(message.id, verdict);
+    }
+}
+
+fn held_status(held: &VecDeque<Held>) -> String {
+    let message = match held.front() {
+        Some(message) => message,
+        None => return String::new(),
+    };
+    let direction = match message.direction {
+        Direction::Request => "request",
+        Direction::Response => "response",
+    };
+    return format!("Holding {} of #{} ({} waiting): f forward, e edit, d drop", direction, message.flow, held.len());
+}
+
+// What the text in the editor replaces when saved.
+enum Editing {
+    // The held message with the ID, given the edited message as its verdict.
+    Held(u64, Direction),
+    // The draft of the request in the flow with the ID.
+    Draft(u64),
+}
+
+// The text edited from the original message as a message, or why it cannot be parsed as one
+// going in the direction. The request method is given for responses.
+fn edited_message(text: &str, original: &[u8], request_method: Option<&str>, direction: Direction) -> Result<Vec<u8>, String> {
+    let data = http_edit::from_text(text, original, request_method);
+    match direction {
+        Direction::Request => match HTTPRequest::new(&data) {
+            Ok(_) => return Ok(data),
+            Err(error) => return Err(format!("The edited request could not be parsed: {}", error)),
+        },
+        Direction::Response => match HTTPResponse::new(&data) {
+            Some(_) => return Ok(data),
+            None => return Err(String::from("The edited response could not be parsed")),
+        },
+    };
+}
+
+// The attempts to repeat the captured request, comparing each response with the response before
+// it, starting with the response captured.
+fn history_lines(captured: &Flow, attempts: &[Flow]) -> Vec<String> {
+    let text = |response: Option<&parse::Request>| response.map(|response| http_edit::to_text(response.data())).unwrap_or_default();
+    let mut previous = text(captured.response.as_ref());
+    let mut lines = Vec::new();
+    for (i, attempt) in attempts.iter().enumerate() {
+        let duration = attempt.duration().map(|duration| format!(" ({} ms)", duration)).unwrap_or_default();
+        let origin = attempt.origin.as_ref().map(|origin| origin.authority.as_str()).unwrap_or("-");
+        lines.push(format!("Attempt {} to {}{}", i + 1, origin, duration));
+        lines.extend(http_edit::to_text(attempt.request.data()).lines().map(|line| format!(">  {}", line)));
+        let response = text(attempt.response.as_ref());
+        for change in diff_lines(&previous, &response) {
+            lines.push(match change {
+                Change::Same(line) => format!("<  {}", line),
+                Change::Added(line) => format!("<+ {}", line),
+                Change::Removed(line) => format!("<- {}", line),
+            });
+        }
+        lines.push(String::new());
+        previous = response;
+    }
+    return lines;
+}
+
+fn history_title(id: u64) -> String {
+    format!("History of #{} (r repeat, e edit, q close)", id)
+}
+
 fn main() {
-    let render = |rows, cols, model: &Vec<parse::Request>, encoding: &Encoding| {
+    // Load the certificate authority before switching to the alternate screen so errors are visible.
+    let authority = match option_value("--ca-dir").map(|directory| CertificateAuthority::load_or_generate(&PathBuf::from(directory))) {
+        Some(Ok(authority)) => Some(authority),
+        Some(Err(e)) => {
+            eprintln!("Failed to load certificate authority: {e}");
+            return;
+        },
+        None => None,
+    };
+    let limits = match limits() {
+        Ok(limits) => limits,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        },
+    };
+
+    // Listen on every --listen <address>, forwarding through --upstream-proxy <address> if given.
+    let mut builder = ProxyBuilder::new().limits(limits);
+    for address in option_values("--listen") {
+        builder = builder.listen(&address);
+    }
+    if let Some(address) = option_value("--upstream-proxy") {
+        builder = builder.upstream_proxy(&address);
+    }
+    if let Some(authority) = authority {
+        builder = builder.intercept(authority);
+    }
+    let (proxy_tx, proxy_rx) = mpsc::channel();
+    let proxy = match builder.start(proxy_tx) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        },
+    };
+
+    let render = |rows: usize, cols, model: &[&Flow], encoding: &Encoding, selected| {
         draw::fill((1,1), rows, cols, Some(TrueColour { red: 0xcb, green: 0xc9, blue: 0xe2 }));
-        draw::log((1,1), model, encoding);
+        draw::log((1,1), rows.saturating_sub(1), model, encoding, selected);
         draw::status_line(rows, cols);
     };
 
     let mut ui = UI::new(Vec::new(), render);
     ui.start();
 
-    let (proxy_tx, proxy_rx) = mpsc::channel();
-    proxy::start("127.0.0.1:7878", proxy_tx);
-
     let (key_tx, key_rx) = mpsc::channel();
     read_commands(key_tx);
 
-    let mut with_option = false;
+    let mut decoder = KeyDecoder::new();
+
+    // Messages held by the proxy, given verdicts in the order they arrived.
+    let mut held: VecDeque<Held> = VecDeque::new();
+    // Edited requests by flow ID.
+    let mut drafts: HashMap<u64, Vec<u8>> = HashMap::new();
+    let mut editing: Option<Editing> = None;
+    // Attempts to repeat requests by flow ID, and the flow whose history is shown.
+    let mut history: HashMap<u64, Vec<Flow>> = HashMap::new();
+    let mut viewing: Option<u64> = None;
+    // Command waiting for its option to be typed and ended with Enter.
+    let mut command: Option<char> = None;
     let mut option = String::from("");
     loop {
         match proxy_rx.try_recv() {
-            Ok(received) => ui.add_data(received),
+            Ok(ProxyEvent::Flow(received)) => {
+                let id = received.id;
+                ui.update_data(*received, |flow| flow.id == id);
+            },
+            Ok(ProxyEvent::Held(message)) => {
+                held.push_back(message);
+                if editing.is_none() {
+                    ui.set_status(held_status(&held));
+                }
+            },
+            Ok(ProxyEvent::Repeated(attempt)) => {
+                let id = attempt.id;
+                let attempts = history.entry(id).or_default();
+                attempts.push(*attempt);
+                if editing.is_none() {
+                    ui.set_status(format!("Repeated #{} ({} attempts)", id, attempts.len()));
+                }
+                if viewing == Some(id) {
+                    if let Some(captured) = ui.data().iter().find(|flow| flow.id == id) {
+                        let lines = history_lines(captured, attempts);
+                        ui.open_page(history_title(id), lines);
+                    }
+                }
+            },
+            Ok(ProxyEvent::Error(error)) => ui.set_status(error.to_string()),
             Err(_) => {},
         };
 
-        if let Ok(key) = key_rx.try_recv() {
-            if key != '\n' && with_option {
-                option.push(key);
-            } else {
-                match key {
-                    'q' => break,
-                    't' => ui.set_encoding(Encoding::Text),
-                    'x' => ui.set_encoding(Encoding::Hex),
-                    'p' => with_option = true,
-                    '\n' => { ui.set_encoding(Encoding::Protocol(option)); with_option = false; option = String::from(""); },
-                    _ => continue,
-                };
-            }
+        let key = match key_rx.try_recv().ok().and_then(|byte| decoder.push(byte)) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        // Ctrl-S saves the text being edited and Ctrl-X discards it.
+        if let Some(edit) = editing.as_ref() {
+            match key {
+                Key::Ctrl('s') => {
+                    let text = ui.close_editor().unwrap_or_default();
+                    match *edit {
+                        Editing::Held(id, direction) => {
+                            let original = held.iter().find(|message| message.id == id);
+                            let request_method = original
+                                .and_then(|message| ui.data().iter().find(|flow| flow.id == message.flow))
+                                .and_then(|flow| HTTPRequest::new(flow.request.data()).ok())
+                                .map(|request| request.method().to_string());
+                            let original = original.map(|message| message.data.clone()).unwrap_or_default();
+                            match edited_message(&text, &original, request_method.as_deref(), direction) {
+                                Ok(data) => {
+                                    held.retain(|message| message.id != id);
+                                    proxy.release(id, Verdict::Edited(data));
+                                    ui.set_status(held_status(&held));
+                                },
+                                Err(error) => {
+                                    ui.open_editor(String::from("Editing held message (Ctrl-S save, Ctrl-X cancel)"), &text);
+                                    ui.set_status(error);
+                                    continue;
+                                },
+                            }
+                        },
+                        Editing::Draft(id) => {
+                            let original = drafts.get(&id).cloned()
+                                .or_else(|| ui.data().iter().find(|flow| flow.id == id).map(|flow| flow.request.data().to_vec()))
+                                .unwrap_or_default();
+                            match edited_message(&text, &original, None, Direction::Request) {
+                                Ok(data) => {
+                                    drafts.insert(id, data);
+                                    ui.set_status(format!("Saved draft of #{}", id));
+                                },
+                                Err(error) => {
+                                    ui.open_editor(format!("Editing request of #{} (Ctrl-S save, Ctrl-X cancel)", id), &text);
+                                    ui.set_status(error);
+                                    continue;
+                                },
+                            }
+                        },
+                    };
+                    editing = None;
+                },
+                Key::Ctrl('x') => {
+                    ui.close_editor();
+                    ui.set_status(held_status(&held));
+                    editing = None;
+                },
+                _ => {
+                    ui.edit(key);
+                },
+            };
+            continue;
+        }
+
+        if command.is_some() {
+            match key {
+                Key::Char(c) => option.push(c),
+                Key::Backspace => {
+                    option.pop();
+                },
+                Key::Enter => {
+                    match command {
+                        Some('p') => ui.set_encoding(Encoding::Protocol(option)),
+                        // Show the flows whose summary, such as a TLS fingerprint, contains the text.
+                        Some('/') => ui.set_filter(option),
+                        Some('m') => {
+                            let mut rules = proxy.hold_rules();
+                            rules.pattern = option;
+                            proxy.set_hold_rules(rules);
+                        },
+                        _ => {},
+                    };
+                    command = None;
+                    option = String::from("");
+                },
+                _ => {},
+            };
+            continue;
+        }
+
+        if ui.is_paging() {
+            match key {
+                Key::Char('j') | Key::Down => {
+                    ui.scroll_page(1);
+                    continue;
+                },
+                Key::Char('k') | Key::Up => {
+                    ui.scroll_page(-1);
+                    continue;
+                },
+                Key::Char('q') | Key::Ctrl('x') => {
+                    viewing = None;
+                    ui.close_page();
+                    continue;
+                },
+                _ => {},
+            };
         }
+
+        match key {
+            Key::Char('q') => break,
+            Key::Char('t') => ui.set_encoding(Encoding::Text),
+            Key::Char('x') => ui.set_encoding(Encoding::Hex),
+            Key::Char(c @ ('p' | 'm' | '/')) => command = Some(c),
+            Key::Char('j') | Key::Down => ui.select_next(),
+            Key::Char('k') | Key::Up => ui.select_previous(),
+            Key::Char('i') => {
+                let mut rules = proxy.hold_rules();
+                let holding = !(rules.requests || rules.responses);
+                rules.requests = holding;
+                rules.responses = holding;
+                proxy.set_hold_rules(rules);
+                if holding {
+                    ui.set_status(String::from("Holding requests and responses"));
+                } else {
+                    // The proxy forwards everything held when holding is turned off.
+                    held.clear();
+                    ui.set_status(String::from("Forwarding without holding"));
+                }
+            },
+            Key::Char('f') => {
+                release(&proxy, &mut held, Verdict::Forward);
+                ui.set_status(held_status(&held));
+            },
+            Key::Char('d') => {
+                release(&proxy, &mut held, Verdict::Drop);
+                ui.set_status(held_status(&held));
+            },
+            // Edit the oldest held message, or else the selected request.
+            Key::Char('e') => {
+                if let Some(message) = held.front() {
+                    editing = Some(Editing::Held(message.id, message.direction));
+                    let text = http_edit::to_text(&message.data);
+                    ui.open_editor(String::from("Editing held message (Ctrl-S save, Ctrl-X cancel)"), &text);
+                } else if let Some(flow) = ui.selected() {
+                    let id = flow.id;
+                    let text = http_edit::to_text(drafts.get(&id).map(|draft| &draft[..]).unwrap_or(flow.request.data()));
+                    editing = Some(Editing::Draft(id));
+                    ui.open_editor(format!("Editing request of #{} (Ctrl-S save, Ctrl-X cancel)", id), &text);
+                }
+            },
+            // Send the draft of the selected request, or else the request captured, to its origin
+            // server again.
+            Key::Char('r') => {
+                if let Some(flow) = ui.selected() {
+                    let request = drafts.get(&flow.id).cloned().unwrap_or_else(|| flow.request.data().to_vec());
+                    let status = match proxy.repeat(flow, request) {
+                        Ok(_) => format!("Repeating #{}", flow.id),
+                        Err(error) => error.to_string(),
+                    };
+                    ui.set_status(status);
+                }
+            },
+            Key::Char('v') => {
+                if let Some(flow) = ui.selected() {
+                    let id = flow.id;
+                    let lines = history_lines(flow, history.get(&id).map(|attempts| &attempts[..]).unwrap_or(&[]));
+                    viewing = Some(id);
+                    ui.open_page(history_title(id), lines);
+                }
+            },
+            _ => {},
+        };
     }
 
     ui.stop();
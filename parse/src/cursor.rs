@@ -0,0 +1,103 @@
+use crate::error::{ParseError, Reason};
+
+// Reads big-endian integers and length-prefixed vectors from a slice without reading past its
+// end. Errors give offsets from the start of the outermost data being parsed.
+#[derive(Clone)]
+pub struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+    // Offset of the slice within the outermost data.
+    base: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Cursor {
+            data: data,
+            position: 0,
+            base: 0,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.base + self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position.min(self.data.len())..]
+    }
+
+    // An error in the field starting at the current offset.
+    pub fn error(&self, field: &'static str, reason: Reason) -> ParseError {
+        ParseError::new(self.offset(), field, reason)
+    }
+
+    pub fn bytes(&mut self, length: usize, field: &'static str) -> Result<&'a [u8], ParseError> {
+        let bytes = match self.remaining().get(..length) {
+            Some(bytes) => bytes,
+            None => return Err(self.error(field, Reason::Truncated)),
+        };
+        self.position += length;
+        return Ok(bytes);
+    }
+
+    pub fn u8(&mut self, field: &'static str) -> Result<u8, ParseError> {
+        Ok(self.bytes(1, field)?[0])
+    }
+
+    pub fn u16(&mut self, field: &'static str) -> Result<u16, ParseError> {
+        let bytes = self.bytes(2, field)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u24(&mut self, field: &'static str) -> Result<u32, ParseError> {
+        let bytes = self.bytes(3, field)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    pub fn u32(&mut self, field: &'static str) -> Result<u32, ParseError> {
+        let bytes = self.bytes(4, field)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Exactly length bytes as a cursor of their own.
+    pub fn take(&mut self, length: usize, field: &'static str) -> Result<Cursor<'a>, ParseError> {
+        let base = self.offset();
+        let data = self.bytes(length, field)?;
+        return Ok(Cursor { data: data, position: 0, base: base });
+    }
+
+    // RFC 8446 3.4 - A vector preceded by its length in bytes, itself encoded in the given number
+    // of bytes.
+    pub fn vector(&mut self, length_bytes: usize, field: &'static str) -> Result<Cursor<'a>, ParseError> {
+        let length_offset = self.offset();
+        let length = match length_bytes {
+            1 => usize::from(self.u8(field)?),
+            2 => usize::from(self.u16(field)?),
+            _ => self.u24(field)? as usize,
+        };
+        if length > self.remaining().len() {
+            return Err(ParseError::new(length_offset, field, Reason::InvalidLength));
+        }
+        return self.take(length, field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_offsets() {
+        let data = [0x00, 0x03, 0x01, 0x02, 0x03, 0x05, 0xFF];
+        let mut cursor = Cursor::new(&data);
+        let mut vector = cursor.vector(2, "vector").unwrap();
+        assert_eq!(vector.u16("first").unwrap(), 0x0102);
+        assert_eq!(vector.u16("second"), Err(ParseError::new(4, "second", Reason::Truncated)));
+        assert_eq!(cursor.vector(1, "next").err(), Some(ParseError::new(5, "next", Reason::InvalidLength)));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::cursor::Cursor;
+use crate::error::{ParseError, Reason};
+
+// ITU-T X.690 - The subset of the Distinguished Encoding Rules used by X.509 certificates: single
+// byte tags and definite lengths.
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const TELETEX_STRING: u8 = 0x14;
+pub const UTC_TIME: u8 = 0x17;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const UNIVERSAL_STRING: u8 = 0x1C;
+pub const BMP_STRING: u8 = 0x1E;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+// A tag and its contents.
+pub struct Element<'a> {
+    pub offset: usize,
+    pub tag: u8,
+    pub contents: Cursor<'a>,
+}
+
+// Read the next element.
+pub fn read<'a>(data: &mut Cursor<'a>, field: &'static str) -> Result<Element<'a>, ParseError> {
+    let offset = data.offset();
+    let tag = data.u8(field)?;
+    // Tags above 30 continue in the following bytes and are not used by certificates.
+    if tag & 0x1F == 0x1F {
+        return Err(ParseError::new(offset, field, Reason::UnknownType(u16::from(tag))));
+    }
+    let length_offset = data.offset();
+    let first = data.u8(field)?;
+    let length = match first {
+        0x00..=0x7F => usize::from(first),
+        // The long form gives the number of length bytes that follow. Indefinite lengths (0x80)
+        // are not allowed by DER.
+        0x81..=0x84 => {
+            let mut length = 0;
+            for byte in data.bytes(usize::from(first & 0x7F), field)? {
+                length = length << 8 | usize::from(*byte);
+            }
+            length
+        },
+        _ => return Err(ParseError::new(length_offset, field, Reason::InvalidLength)),
+    };
+    if length > data.remaining().len() {
+        return Err(ParseError::new(length_offset, field, Reason::InvalidLength));
+    }
+    return Ok(Element {
+        offset: offset,
+        tag: tag,
+        contents: data.take(length, field)?,
+    });
+}
+
+// Read the next element, which must have the tag, returning its contents.
+pub fn expect<'a>(data: &mut Cursor<'a>, tag: u8, field: &'static str) -> Result<Cursor<'a>, ParseError> {
+    let element = read(data, field)?;
+    if element.tag != tag {
+        return Err(ParseError::new(element.offset, field, Reason::Malformed));
+    }
+    return Ok(element.contents);
+}
+
+// Read the next element if it has the tag, as for OPTIONAL and DEFAULT fields.
+pub fn optional<'a>(data: &mut Cursor<'a>, tag: u8, field: &'static str) -> Result<Option<Cursor<'a>>, ParseError> {
+    match data.remaining().first() {
+        Some(next) if *next == tag => Ok(Some(expect(data, tag, field)?)),
+        _ => Ok(None),
+    }
+}
+
+// X.690 8.19 - The dotted form of an object identifier. The first two components share the first
+// byte, and every component is base 128 with the high bit set on all but its last byte.
+pub fn object_identifier(contents: &Cursor, field: &'static str) -> Result<String, ParseError> {
+    let bytes = contents.remaining();
+    let mut components: Vec<u64> = Vec::new();
+    let mut component: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if component > u64::MAX >> 7 {
+            return Err(ParseError::new(contents.offset() + i, field, Reason::Malformed));
+        }
+        component = component << 7 | u64::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            if components.is_empty() {
+                let first = (component / 40).min(2);
+                components.push(first);
+                components.push(component - first * 40);
+            } else {
+                components.push(component);
+            }
+            component = 0;
+        }
+    }
+    if components.is_empty() || bytes.last().map(|byte| byte & 0x80 != 0) == Some(true) {
+        return Err(contents.error(field, Reason::Malformed));
+    }
+    return Ok(components.iter().map(|component| component.to_string()).collect::<Vec<_>>().join("."));
+}
+
+// The text of a string element. Strings that are not valid in their encoding are decoded
+// lossily, as names are shown rather than compared.
+pub fn string(element: &Element) -> String {
+    let bytes = element.contents.remaining();
+    match element.tag {
+        // Latin-1 is close enough for the T.61 strings found in older certificates.
+        TELETEX_STRING => bytes.iter().map(|byte| char::from(*byte)).collect(),
+        BMP_STRING => {
+            let units: Vec<u16> = bytes.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+            String::from_utf16_lossy(&units)
+        },
+        UNIVERSAL_STRING => bytes.chunks_exact(4)
+            .map(|unit| char::from_u32(u32::from_be_bytes([unit[0], unit[1], unit[2], unit[3]])).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+        // UTF8String, PrintableString and IA5String. The last two are ASCII.
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+// The size in bits of an unsigned big-endian integer, such as a key modulus. INTEGER contents
+// have a leading zero byte when the high bit is set, to keep them positive.
+pub fn bit_length(integer: &[u8]) -> usize {
+    match integer.iter().position(|byte| *byte != 0) {
+        Some(i) => (integer.len() - i) * 8 - integer[i].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+// RFC 5280 4.1.2.5 - A UTCTime or GeneralizedTime as "YYYY-MM-DD HH:MM:SS UTC". UTCTime years
+// from 50 are in the 1900s.
+pub fn time(element: &Element, field: &'static str) -> Result<String, ParseError> {
+    let text = element.contents.remaining();
+    let digits = match element.tag {
+        UTC_TIME if text.len() == 13 => {
+            let century: &[u8] = if text[0] >= b'5' { b"19" } else { b"20" };
+            [century, &text[..12]].concat()
+        },
+        GENERALIZED_TIME if text.len() == 15 => text[..14].to_vec(),
+        _ => return Err(ParseError::new(element.offset, field, Reason::Malformed)),
+    };
+    if !digits.iter().all(|digit| digit.is_ascii_digit()) || text.last() != Some(&b'Z') {
+        return Err(ParseError::new(element.offset, field, Reason::Malformed));
+    }
+    let digits = String::from_utf8_lossy(&digits);
+    return Ok(format!("{}-{}-{} {}:{}:{} UTC", &digits[0..4], &digits[4..6], &digits[6..8], &digits[8..10], &digits[10..12], &digits[12..14]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_lengths() {
+        // A long form length, followed by an element longer than the data.
+        let mut data = vec![0x04, 0x81, 0x80];
+        data.extend_from_slice(&[0xAA; 0x80]);
+        data.extend_from_slice(&[0x30, 0x05, 0x00]);
+        let mut cursor = Cursor::new(&data);
+        let element = read(&mut cursor, "first").unwrap();
+        assert_eq!((element.tag, element.contents.remaining().len()), (OCTET_STRING, 0x80));
+        assert_eq!(read(&mut cursor, "second").err(), Some(ParseError::new(0x84, "second", Reason::InvalidLength)));
+    }
+
+    #[test]
+    fn object_identifiers() {
+        let data = [0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B];
+        let contents = expect(&mut Cursor::new(&data), OBJECT_IDENTIFIER, "oid").unwrap();
+        assert_eq!(object_identifier(&contents, "oid").unwrap(), "1.2.840.113549.1.1.11");
+        let data = [0x06, 0x02, 0x2A, 0x86];
+        let contents = expect(&mut Cursor::new(&data), OBJECT_IDENTIFIER, "oid").unwrap();
+        assert_eq!(object_identifier(&contents, "oid").err(), Some(ParseError::new(2, "oid", Reason::Malformed)));
+    }
+
+    #[test]
+    fn bit_lengths() {
+        assert_eq!(bit_length(&[0x00, 0x80, 0x00]), 16);
+        assert_eq!(bit_length(&[0x01]), 1);
+        assert_eq!(bit_length(&[0x00]), 0);
+    }
+
+    #[test]
+    fn times() {
+        let data = b"\x17\x0D491231235959Z\x18\x0F20500101000000Z";
+        let mut cursor = Cursor::new(data);
+        assert_eq!(time(&read(&mut cursor, "time").unwrap(), "time").unwrap(), "2049-12-31 23:59:59 UTC");
+        assert_eq!(time(&read(&mut cursor, "time").unwrap(), "time").unwrap(), "2050-01-01 00:00:00 UTC");
+    }
+}
@@ -0,0 +1,64 @@
+// A line in the comparison of two texts.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Change<'a> {
+    Same(&'a str),
+    // In the new text only.
+    Added(&'a str),
+    // In the old text only.
+    Removed(&'a str),
+}
+
+// Compare two texts line by line, keeping the longest common subsequence of lines unchanged.
+// Removed lines are listed before the lines added in their place.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push(Change::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(Change::Removed(old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j]));
+            j += 1;
+        }
+    }
+    return changes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_header() {
+        let old = "HTTP/1.1 200 OK\nAge: 1\nContent-Length: 2\n\nOK";
+        let new = "HTTP/1.1 200 OK\nAge: 5\nContent-Length: 2\n\nOK\n";
+        assert_eq!(diff_lines(old, new), vec![
+            Change::Same("HTTP/1.1 200 OK"),
+            Change::Removed("Age: 1"),
+            Change::Added("Age: 5"),
+            Change::Same("Content-Length: 2"),
+            Change::Same(""),
+            Change::Same("OK"),
+        ]);
+    }
+}
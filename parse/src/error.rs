@@ -0,0 +1,54 @@
+use std::fmt;
+
+// Why a message could not be parsed, and where.
+#[derive(Clone,Debug,PartialEq)]
+pub struct ParseError {
+    // Offset of the byte where parsing failed from the start of the parsed data.
+    pub offset: usize,
+    // Name of the field being parsed, as named by the specification of the protocol.
+    pub field: &'static str,
+    pub reason: Reason,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Reason {
+    // The data ends before the field does.
+    Truncated,
+    // A length prefix exceeds the data containing it or does not fit the field.
+    InvalidLength,
+    InvalidUtf8,
+    // A type identifier without a definition.
+    UnknownType(u16),
+    // The field does not follow the syntax of the protocol.
+    Malformed,
+}
+
+impl ParseError {
+    pub fn new(offset: usize, field: &'static str, reason: Reason) -> Self {
+        ParseError {
+            offset: offset,
+            field: field,
+            reason: reason,
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Reason::Truncated => write!(f, "truncated"),
+            Reason::InvalidLength => write!(f, "invalid length"),
+            Reason::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            Reason::UnknownType(n) => write!(f, "unknown type {}", n),
+            Reason::Malformed => write!(f, "malformed"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{} at byte {}: {}", self.field, self.offset, self.reason)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,150 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::tls_extension::{is_grease, Extension, SupportedVersions};
+use crate::tls_request::{ClientHello, HandshakeMessage, RecordReader, Message};
+
+// Fingerprints of the TLS client that sent a ClientHello. GREASE values are left out of both so
+// that clients choosing them at random keep the same fingerprints.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Fingerprint {
+    // The fields JA3 hashes, and their MD5 hash.
+    pub ja3_string: String,
+    pub ja3: String,
+    pub ja4: String,
+}
+
+impl Fingerprint {
+    pub fn new(hello: &ClientHello) -> Self {
+        let ja3_string = ja3_string(hello);
+        Fingerprint {
+            ja3: hex(&Md5::digest(ja3_string.as_bytes())),
+            ja3_string: ja3_string,
+            ja4: ja4(hello),
+        }
+    }
+
+    // The fingerprint of the first ClientHello in the capture, if it starts with one.
+    pub fn from_capture(data: &[u8]) -> Option<Self> {
+        let record = RecordReader::new(data).next()?.ok()?;
+        return record.messages.iter().find_map(|message| match message {
+            Message::Handshake(handshake) => match handshake.msg {
+                Some(HandshakeMessage::ClientHello(ref hello)) => Some(Fingerprint::new(hello)),
+                _ => None,
+            },
+            _ => None,
+        });
+    }
+}
+
+// JA3 - The version, cipher suites, extensions, supported groups and point formats in the order
+// sent, as decimal values joined with dashes, and the fields joined with commas.
+fn ja3_string(hello: &ClientHello) -> String {
+    let mut groups: Vec<u16> = Vec::new();
+    let mut point_formats: Vec<u16> = Vec::new();
+    for extension in hello.extensions.iter() {
+        match extension {
+            Extension::SupportedGroups(list) => groups = list.iter().map(|group| group.id()).collect(),
+            Extension::ECPointFormats(list) => point_formats = list.iter().map(|format| u16::from(format.id())).collect(),
+            _ => {},
+        };
+    }
+    let fields = [
+        vec![hello.legacy_version],
+        hello.cipher_suites.iter().map(|cipher_suite| cipher_suite.id()).collect(),
+        hello.extensions.iter().map(|extension| extension.id()).collect(),
+        groups,
+        point_formats,
+    ];
+    return fields.iter()
+        .map(|field| field.iter().filter(|value| !is_grease(**value)).map(|value| value.to_string()).collect::<Vec<_>>().join("-"))
+        .collect::<Vec<_>>()
+        .join(",");
+}
+
+// JA4 - Three parts joined with underscores: a summary of the hello, a hash of the sorted cipher
+// suites, and a hash of the sorted extensions followed by the signature algorithms in the order
+// sent. Hashes are the first 12 hex digits of SHA-256.
+fn ja4(hello: &ClientHello) -> String {
+    let cipher_suites: Vec<u16> = hello.cipher_suites.iter().map(|cipher_suite| cipher_suite.id()).filter(|id| !is_grease(*id)).collect();
+    let extensions: Vec<u16> = hello.extensions.iter().map(|extension| extension.id()).filter(|id| !is_grease(*id)).collect();
+
+    // Versions offered in the extension take precedence over the legacy version.
+    let mut version = hello.legacy_version;
+    let mut server_name = 'i';
+    let mut alpn = String::from("00");
+    let mut signature_algorithms: Vec<u16> = Vec::new();
+    for extension in hello.extensions.iter() {
+        match extension {
+            Extension::SupportedVersions(SupportedVersions::Offered(versions)) => {
+                version = versions.iter().map(|version| version.id()).filter(|id| !is_grease(*id)).max().unwrap_or(version);
+            },
+            Extension::ServerName(_) | Extension::Undecoded { id: 0, .. } => server_name = 'd',
+            Extension::ApplicationLayerProtocolNegotiation(list) => {
+                if let Some(protocol) = list.protocol_name_list.first() {
+                    alpn = alpn_characters(protocol.as_bytes());
+                }
+            },
+            Extension::SignatureAlgorithms(schemes) => {
+                signature_algorithms = schemes.iter().map(|scheme| scheme.id()).filter(|id| !is_grease(*id)).collect();
+            },
+            _ => {},
+        };
+    }
+    let version = match version {
+        0x0304 => "13",
+        0x0303 => "12",
+        0x0302 => "11",
+        0x0301 => "10",
+        0x0300 => "s3",
+        0x0002 => "s2",
+        0xFEFF => "d1",
+        0xFEFD => "d2",
+        0xFEFC => "d3",
+        _ => "00",
+    };
+    let summary = format!("t{}{}{:02}{:02}{}", version, server_name, cipher_suites.len().min(99), extensions.len().min(99), alpn);
+
+    let mut sorted_cipher_suites = cipher_suites.clone();
+    sorted_cipher_suites.sort();
+    // The server name and ALPN are counted in the summary but left out of the hash.
+    let mut sorted_extensions: Vec<u16> = extensions.iter().copied().filter(|id| *id != 0x0000 && *id != 0x0010).collect();
+    sorted_extensions.sort();
+    let mut extensions_field = hex_list(&sorted_extensions);
+    if !signature_algorithms.is_empty() {
+        extensions_field.push('_');
+        extensions_field.push_str(&hex_list(&signature_algorithms));
+    }
+
+    let cipher_suites_hash = if cipher_suites.is_empty() { String::from("000000000000") } else { truncated_hash(&hex_list(&sorted_cipher_suites)) };
+    let extensions_hash = if extensions.is_empty() { String::from("000000000000") } else { truncated_hash(&extensions_field) };
+    return format!("{}_{}_{}", summary, cipher_suites_hash, extensions_hash);
+}
+
+// The first and last characters of the protocol, or of its hex digits if either is not
+// alphanumeric.
+fn alpn_characters(protocol: &[u8]) -> String {
+    let (first, last) = match (protocol.first(), protocol.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return String::from("00"),
+    };
+    if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+        return format!("{}{}", first as char, last as char);
+    }
+    let digits = hex(protocol);
+    return format!("{}{}", &digits[..1], &digits[digits.len() - 1..]);
+}
+
+fn hex_list(values: &[u16]) -> String {
+    values.iter().map(|value| format!("{:04x}", value)).collect::<Vec<_>>().join(",")
+}
+
+fn truncated_hash(text: &str) -> String {
+    let mut digest = hex(&Sha256::digest(text.as_bytes()));
+    digest.truncate(12);
+    return digest;
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
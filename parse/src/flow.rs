@@ -0,0 +1,158 @@
+use std::fmt;
+use std::net::SocketAddr;
+
+use tui::draw;
+use tui::Encoding;
+
+use crate::Request;
+use crate::fingerprint::Fingerprint;
+use crate::tls_alert::Alert;
+
+// An exchange between a client and an upstream server passing through the proxy: the data sent by
+// the client, the data sent back by the server, and metadata about the connection. Flows sharing a
+// client connection have the same connection ID.
+#[derive(Clone)]
+pub struct Flow {
+    pub id: u64,
+    pub connection: u64,
+    pub client: SocketAddr,
+    pub upstream: Option<SocketAddr>,
+    // The origin server the request was relayed to, if it was relayed as an HTTP request.
+    pub origin: Option<Origin>,
+    pub request: Request,
+    pub response: Option<Request>,
+    pub timings: Timings,
+    // Fingerprint of the TLS client, if the request is a ClientHello.
+    pub fingerprint: Option<Fingerprint>,
+    // TLS alerts starting the request or the response, such as a client rejecting the
+    // certificate of the server.
+    pub client_alert: Option<Alert>,
+    pub server_alert: Option<Alert>,
+}
+
+// Where a request was sent: the authority of the origin server, and the server name of the TLS
+// session it was sent in, if any.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Origin {
+    pub authority: String,
+    pub server_name: Option<String>,
+}
+
+// Milliseconds since the Unix epoch at which each stage of a flow was reached.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Timings {
+    pub request: u128,
+    pub upstream_connected: Option<u128>,
+    pub response_start: Option<u128>,
+    pub response_end: Option<u128>,
+}
+
+impl Flow {
+    pub fn new(id: u64, connection: u64, client: SocketAddr, request: Request) -> Self {
+        let timings = Timings {
+            request: request.timestamp,
+            upstream_connected: None,
+            response_start: None,
+            response_end: None,
+        };
+
+        let fingerprint = Fingerprint::from_capture(&request.data);
+        let client_alert = Alert::from_capture(&request.data);
+
+        Flow {
+            id: id,
+            connection: connection,
+            client: client,
+            upstream: None,
+            origin: None,
+            request: request,
+            response: None,
+            timings: timings,
+            fingerprint: fingerprint,
+            client_alert: client_alert,
+            server_alert: None,
+        }
+    }
+
+    pub fn connected(&mut self, timestamp: u128, upstream: Option<SocketAddr>) {
+        self.upstream = upstream;
+        self.timings.upstream_connected = Some(timestamp);
+    }
+
+    // Append data received from the upstream server, starting the response if necessary.
+    pub fn extend_response(&mut self, timestamp: u128, data: &[u8]) {
+        match self.response {
+            Some(ref mut response) => response.data.extend_from_slice(data),
+            None => {
+                self.response = Some(Request::new(timestamp, data.to_vec()));
+                self.timings.response_start = Some(timestamp);
+            },
+        };
+        if self.server_alert.is_none() {
+            self.server_alert = self.response.as_ref().and_then(|response| Alert::from_capture(&response.data));
+        }
+    }
+
+    // Replace the request with an edited version sent in its place.
+    pub fn edit_request(&mut self, data: Vec<u8>) {
+        self.fingerprint = Fingerprint::from_capture(&data);
+        self.client_alert = Alert::from_capture(&data);
+        self.request.data = data;
+    }
+
+    // Replace the response with an edited version sent in its place.
+    pub fn edit_response(&mut self, data: Vec<u8>) {
+        self.server_alert = Alert::from_capture(&data);
+        match self.response {
+            Some(ref mut response) => response.data = data,
+            None => self.response = Some(Request::new(self.timings.request, data)),
+        };
+    }
+
+    pub fn complete(&mut self, timestamp: u128) {
+        self.timings.response_end = Some(timestamp);
+    }
+
+    // Milliseconds from receiving the request to the end of the response.
+    pub fn duration(&self) -> Option<u128> {
+        self.timings.response_end.map(|end| end.saturating_sub(self.timings.request))
+    }
+}
+
+impl fmt::Display for Flow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "#{} connection {} {} -> ", self.id, self.connection, self.client)?;
+        match self.upstream {
+            Some(upstream) => write!(f, "{}", upstream)?,
+            None => write!(f, "-")?,
+        };
+        if let Some(duration) = self.duration() {
+            write!(f, " ({} ms)", duration)?;
+        }
+        if let Some(ref fingerprint) = self.fingerprint {
+            write!(f, " ja4={} ja3={}", fingerprint.ja4, fingerprint.ja3)?;
+        }
+        if let Some(ref alert) = self.client_alert {
+            write!(f, " alert={} (client)", alert)?;
+        }
+        if let Some(ref alert) = self.server_alert {
+            write!(f, " alert={} (server)", alert)?;
+        }
+        Ok(())
+    }
+}
+
+impl draw::LogEntry for Flow {
+    fn timestamp(&self) -> String {
+        format!("{}", self.timings.request)
+    }
+
+    fn to_lines(&self, encoding: &Encoding) -> Vec<String> {
+        let mut lines = vec![self.to_string()];
+        lines.extend(self.request.to_lines(encoding).into_iter().map(|line| String::from("> ") + &line));
+        if let Some(ref response) = self.response {
+            lines.extend(response.to_lines(encoding).into_iter().map(|line| String::from("< ") + &line));
+        }
+        return lines;
+    }
+}
@@ -0,0 +1,130 @@
+// Converts HTTP/1 messages to and from the text shown in an editor. Lines in the head end in LF
+// while editing so that line endings need not be typed, and in CRLF when sent.
+
+const CRLF: &[u8] = b"\r\n";
+
+// The head with LF line endings followed by the body. Bytes that are not UTF-8 are shown replaced
+// and kept by from_text if the body is left unchanged.
+pub fn to_text(data: &[u8]) -> String {
+    let (head, body) = match data.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(i) => (&data[..i], &data[i + 4..]),
+        None => return String::from_utf8_lossy(data).replace("\r\n", "\n"),
+    };
+    let mut text = String::from_utf8_lossy(head).replace("\r\n", "\n");
+    text.push_str("\n\n");
+    text.push_str(&String::from_utf8_lossy(body));
+    return text;
+}
+
+// The message for the text edited from the original message. The head ends at the first empty
+// line. The body is sent as typed, or as in the original message if it was left unchanged, so
+// that bodies that are not UTF-8 are kept. The request method is given for responses.
+//
+// RFC 9112 6.3 - Content-Length is set to the length of the body if the message had one or has
+// a body, unless the body is chunked. Responses that never have a body, those with status 1xx,
+// 204 or 304 and those answering HEAD, keep the Content-Length they were given.
+pub fn from_text(text: &str, original: &[u8], request_method: Option<&str>) -> Vec<u8> {
+    let (head, body) = match text.find("\n\n") {
+        Some(i) => (&text[..i], &text[i + 2..]),
+        None => (text.trim_end_matches('\n'), ""),
+    };
+    let mut lines: Vec<String> = head.split('\n')
+        .map(|line| line.trim_end_matches('\r').to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let original_body = match original.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(i) => &original[i + 4..],
+        None => &[],
+    };
+    let body = if body == String::from_utf8_lossy(original_body) { original_body } else { body.as_bytes() };
+
+    let status_code = lines.first()
+        .filter(|line| line.starts_with("HTTP/"))
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status_code| status_code.parse::<u16>().ok());
+    let without_body = match status_code {
+        Some(status_code) => status_code < 200 || status_code == 204 || status_code == 304 || request_method == Some("HEAD"),
+        None => false,
+    };
+    let chunked = lines.iter().skip(1).any(|line| {
+        field_name(line).eq_ignore_ascii_case("Transfer-Encoding") && line.to_ascii_lowercase().contains("chunked")
+    });
+    if !chunked && !without_body {
+        // Only the first Content-Length line is kept, so that no other can disagree with it.
+        let mut replaced = false;
+        for line in lines.iter_mut().skip(1) {
+            if field_name(line).eq_ignore_ascii_case("Content-Length") {
+                if replaced {
+                    line.clear();
+                } else {
+                    *line = format!("Content-Length: {}", body.len());
+                    replaced = true;
+                }
+            }
+        }
+        lines.retain(|line| !line.is_empty());
+        if !replaced && !body.is_empty() {
+            lines.push(format!("Content-Length: {}", body.len()));
+        }
+    }
+
+    let mut data = Vec::new();
+    for line in lines.iter() {
+        data.extend_from_slice(line.as_bytes());
+        data.extend_from_slice(CRLF);
+    }
+    data.extend_from_slice(CRLF);
+    data.extend_from_slice(body);
+    return data;
+}
+
+fn field_name(line: &str) -> &str {
+    line.split(':').next().unwrap_or("").trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_round_trip() {
+        let request = b"POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 9\r\n\r\nAAAA\nBBBB";
+        let text = to_text(request);
+        assert_eq!(text, "POST /a HTTP/1.1\nHost: example.com\nContent-Length: 9\n\nAAAA\nBBBB");
+        assert_eq!(from_text(&text, request, None), request.to_vec());
+    }
+
+    #[test]
+    fn content_length_recomputed() {
+        let edited = from_text("POST / HTTP/1.1\ncontent-length: 1\n\nAAAA=BBBB", b"", None);
+        assert_eq!(edited, b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\nAAAA=BBBB".to_vec());
+
+        let edited = from_text("PUT / HTTP/1.1\nHost: a\n\nAB", b"", None);
+        assert_eq!(edited, b"PUT / HTTP/1.1\r\nHost: a\r\nContent-Length: 2\r\n\r\nAB".to_vec());
+
+        let edited = from_text("GET / HTTP/1.1\nHost: a\n", b"", None);
+        assert_eq!(edited, b"GET / HTTP/1.1\r\nHost: a\r\n\r\n".to_vec());
+
+        let chunked = "POST / HTTP/1.1\nTransfer-Encoding: chunked\n\n2\r\nAB\r\n0\r\n\r\n";
+        assert_eq!(from_text(chunked, b"", None), b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nAB\r\n0\r\n\r\n".to_vec());
+    }
+
+    #[test]
+    fn binary_body_kept() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 4\r\n\r\n\x1F\x8B\x08\xFF";
+        let text = to_text(response).replace("gzip", "br");
+        assert_eq!(from_text(&text, response, Some("GET")), b"HTTP/1.1 200 OK\r\nContent-Encoding: br\r\nContent-Length: 4\r\n\r\n\x1F\x8B\x08\xFF".to_vec());
+    }
+
+    #[test]
+    fn content_length_without_body() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 12\r\n\r\n";
+        assert_eq!(from_text(&to_text(response), response, Some("HEAD")), response.to_vec());
+        let response = b"HTTP/1.1 304 Not Modified\r\nContent-Length: 12\r\n\r\n";
+        assert_eq!(from_text(&to_text(response), response, Some("GET")), response.to_vec());
+
+        // Repeated Content-Length lines are replaced by one.
+        let edited = from_text("HTTP/1.1 200 OK\nContent-Length: 2\nX: 1\nContent-Length: 2\n\nABC", b"", Some("GET"));
+        assert_eq!(edited, b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nX: 1\r\n\r\nABC".to_vec());
+    }
+}
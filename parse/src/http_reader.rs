@@ -0,0 +1,368 @@
+use crate::http_request::HTTPRequest;
+use crate::http_response::HTTPResponse;
+
+// RFC 9112 6 - Progress towards delimiting the next message read from a connection.
+#[derive(Debug,PartialEq)]
+pub enum Framing {
+    // More bytes are required to find the end of the message.
+    Incomplete,
+    // The message ends at the offset.
+    Complete(usize),
+    // The message ends when the connection is closed.
+    UntilClose,
+    // The message cannot be delimited.
+    Invalid,
+}
+
+// Longest head accepted, so that a peer that never ends its head cannot make the reader buffer
+// without limit.
+pub const MAX_HEAD_LENGTH: usize = 0x10000;
+
+#[derive(Debug,PartialEq)]
+enum State {
+    // The head does not end before the offset, up to which it has been searched.
+    Head(usize),
+    // The message ends at the offset.
+    Length(usize),
+    // The next chunk size line starts at the offset.
+    Chunk(usize),
+    // The next trailer field line starts at the offset.
+    Trailer(usize),
+    UntilClose,
+    Invalid,
+}
+
+// Splits the bytes received on a connection into discrete HTTP/1 messages as they arrive.
+pub struct MessageReader {
+    response: bool,
+    // Responses to HEAD and CONNECT requests are delimited differently.
+    request_method: String,
+    buffer: Vec<u8>,
+    state: State,
+}
+
+impl MessageReader {
+    pub fn request() -> Self {
+        MessageReader {
+            response: false,
+            request_method: String::new(),
+            buffer: Vec::new(),
+            state: State::Head(0),
+        }
+    }
+
+    pub fn response() -> Self {
+        MessageReader {
+            response: true,
+            request_method: String::from("GET"),
+            buffer: Vec::new(),
+            state: State::Head(0),
+        }
+    }
+
+    // Method of the request answered by the next response.
+    pub fn set_request_method(&mut self, method: &str) {
+        self.request_method = method.to_string();
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    // Bytes received but not yet returned as part of a message.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    // Remove and return everything buffered, such as the start of a tunnel following CONNECT.
+    pub fn take_buffered(&mut self) -> Vec<u8> {
+        self.state = State::Head(0);
+        std::mem::take(&mut self.buffer)
+    }
+
+    // Remove and return the next message if all of it has been received.
+    pub fn next_message(&mut self) -> Option<Vec<u8>> {
+        match self.framing() {
+            Framing::Complete(length) => {
+                self.state = State::Head(0);
+                let rest = self.buffer.split_off(length);
+                return Some(std::mem::replace(&mut self.buffer, rest));
+            },
+            _ => return None,
+        }
+    }
+
+    // Remove and return whatever remains once the connection is closed. This completes messages
+    // delimited by the end of the connection and returns truncated messages as received.
+    pub fn finish(&mut self) -> Option<Vec<u8>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        return Some(self.take_buffered());
+    }
+
+    pub fn framing(&mut self) -> Framing {
+        loop {
+            match self.state {
+                State::Head(searched) => {
+                    // RFC 9112 2.2 - Empty lines received before a request line are ignored.
+                    let mut searched = searched;
+                    if !self.response {
+                        while self.buffer.starts_with(b"\r\n") {
+                            self.buffer.drain(..2);
+                            searched = searched.saturating_sub(2);
+                        }
+                    }
+                    // The end of the head may have started in the bytes already searched.
+                    let start = searched.saturating_sub(3);
+                    let head_length = match self.buffer[start..].windows(4).position(|window| window == b"\r\n\r\n") {
+                        Some(i) => start + i + 4,
+                        None if self.buffer.len() > MAX_HEAD_LENGTH => return Framing::Invalid,
+                        None => {
+                            self.state = State::Head(self.buffer.len());
+                            return Framing::Incomplete;
+                        },
+                    };
+                    if head_length > MAX_HEAD_LENGTH {
+                        self.state = State::Invalid;
+                    } else {
+                        self.state = self.body_state(head_length);
+                    }
+                },
+                State::Length(length) => {
+                    if self.buffer.len() >= length {
+                        return Framing::Complete(length);
+                    }
+                    return Framing::Incomplete;
+                },
+                State::Chunk(offset) => {
+                    let line_length = match find_line(&self.buffer[offset..]) {
+                        Some(line_length) => line_length,
+                        None => return Framing::Incomplete,
+                    };
+                    // RFC 9112 7.1 - The size is hex digits only, followed by any extensions.
+                    let size = std::str::from_utf8(&self.buffer[offset..offset + line_length]).ok()
+                        .and_then(|line| line.split(';').next())
+                        .map(|size| size.trim_end_matches([' ', '\t']))
+                        .filter(|size| !size.is_empty() && size.bytes().all(|digit| digit.is_ascii_hexdigit()))
+                        .and_then(|size| usize::from_str_radix(size, 16).ok());
+                    self.state = match size {
+                        Some(0) => State::Trailer(offset + line_length + 2),
+                        // The chunk data is followed by CRLF.
+                        Some(size) => match (offset + line_length + 2).checked_add(size).and_then(|end| end.checked_add(2)) {
+                            Some(next) => {
+                                if self.buffer.len() < next {
+                                    return Framing::Incomplete;
+                                }
+                                if &self.buffer[next - 2..next] != b"\r\n" {
+                                    State::Invalid
+                                } else {
+                                    State::Chunk(next)
+                                }
+                            },
+                            None => State::Invalid,
+                        },
+                        None => State::Invalid,
+                    };
+                },
+                State::Trailer(offset) => {
+                    let line_length = match find_line(&self.buffer[offset..]) {
+                        Some(line_length) => line_length,
+                        None => return Framing::Incomplete,
+                    };
+                    if line_length == 0 {
+                        self.state = State::Length(offset + 2);
+                    } else {
+                        self.state = State::Trailer(offset + line_length + 2);
+                    }
+                },
+                State::UntilClose => return Framing::UntilClose,
+                State::Invalid => return Framing::Invalid,
+            };
+        }
+    }
+
+    // RFC 9112 6.3 - Determine how the body following the head is delimited.
+    fn body_state(&self, head_length: usize) -> State {
+        let head = &self.buffer[..head_length];
+        let headers = if self.response {
+            let response = match HTTPResponse::new(head) {
+                Some(response) => response,
+                None => return State::Invalid,
+            };
+            let status_code = response.status_code();
+            if self.request_method == "HEAD" || status_code < 200 || status_code == 204 || status_code == 304 {
+                return State::Length(head_length);
+            }
+            // A successful CONNECT turns the connection into a tunnel.
+            if self.request_method == "CONNECT" && status_code < 300 {
+                return State::Length(head_length);
+            }
+            response.headers().to_vec()
+        } else {
+            match HTTPRequest::new(head) {
+                Ok(request) => request.headers().to_vec(),
+                Err(_) => return State::Invalid,
+            }
+        };
+
+        // Repeated field lines are combined as a comma separated list.
+        let transfer_encoding: Vec<&str> = headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Transfer-Encoding"))
+            .flat_map(|(_, value)| value.split(','))
+            .collect();
+        if !transfer_encoding.is_empty() {
+            // Only a final chunked coding delimits the body. Otherwise a response ends when the
+            // connection closes and a request cannot be delimited.
+            if transfer_encoding.last().unwrap_or(&"").trim().eq_ignore_ascii_case("chunked") {
+                return State::Chunk(head_length);
+            } else if self.response {
+                return State::UntilClose;
+            } else {
+                return State::Invalid;
+            }
+        }
+
+        // Repeated values are accepted if they are all the same. A message with differing values
+        // cannot be delimited.
+        let mut content_length = None;
+        for value in headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+            .flat_map(|(_, value)| value.split(',')) {
+            // RFC 9110 8.6 - Digits only, which parse would accept after a sign.
+            let value = value.trim_matches([' ', '\t']);
+            if value.is_empty() || !value.bytes().all(|digit| digit.is_ascii_digit()) {
+                return State::Invalid;
+            }
+            let length = match value.parse::<usize>() {
+                Ok(length) => length,
+                Err(_) => return State::Invalid,
+            };
+            if content_length.is_some() && content_length != Some(length) {
+                return State::Invalid;
+            }
+            content_length = Some(length);
+        }
+        match content_length {
+            Some(length) => match head_length.checked_add(length) {
+                Some(end) => State::Length(end),
+                None => State::Invalid,
+            },
+            // Requests without framing fields have no body.
+            None if !self.response => State::Length(head_length),
+            None => State::UntilClose,
+        }
+    }
+}
+
+// Length of the line at the start of the data, excluding the CRLF ending it.
+fn find_line(data: &[u8]) -> Option<usize> {
+    data.windows(2).position(|window| window == b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http_reader::{Framing, MessageReader, MAX_HEAD_LENGTH};
+
+    #[test]
+    fn pipelined_requests() {
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nAA");
+        assert_eq!(reader.framing(), Framing::Incomplete);
+        assert_eq!(reader.next_message(), None);
+        reader.push(b"AAGET / HTTP/1.1\r\n\r\nGET");
+        assert_eq!(reader.next_message(), Some(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nAAAA".to_vec()));
+        assert_eq!(reader.next_message(), Some(b"GET / HTTP/1.1\r\n\r\n".to_vec()));
+        assert_eq!(reader.next_message(), None);
+        assert_eq!(reader.buffered(), b"GET");
+    }
+
+    #[test]
+    fn chunked_request() {
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nAAAA\r\n");
+        assert_eq!(reader.framing(), Framing::Incomplete);
+        reader.push(b"0\r\nTrailer: value\r\n");
+        assert_eq!(reader.framing(), Framing::Incomplete);
+        reader.push(b"\r\n");
+        assert_eq!(reader.framing(), Framing::Complete(77));
+    }
+
+    #[test]
+    fn close_delimited_response() {
+        let mut reader = MessageReader::response();
+        reader.push(b"HTTP/1.0 200 OK\r\n\r\nAAAA");
+        assert_eq!(reader.framing(), Framing::UntilClose);
+        assert_eq!(reader.next_message(), None);
+        assert_eq!(reader.finish(), Some(b"HTTP/1.0 200 OK\r\n\r\nAAAA".to_vec()));
+    }
+
+    #[test]
+    fn head_response() {
+        let mut reader = MessageReader::response();
+        reader.set_request_method("HEAD");
+        reader.push(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n");
+        assert_eq!(reader.framing(), Framing::Complete(38));
+    }
+
+    #[test]
+    fn invalid_content_length() {
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nContent-Length: A\r\n\r\n");
+        assert_eq!(reader.framing(), Framing::Invalid);
+    }
+
+    #[test]
+    fn repeated_content_length() {
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 4, 4\r\n\r\nAAAA");
+        assert_eq!(reader.framing(), Framing::Complete(64));
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 2\r\n\r\nAAAA");
+        assert_eq!(reader.framing(), Framing::Invalid);
+    }
+
+    #[test]
+    fn signed_lengths() {
+        for head in [&b"Content-Length: +4\r\n"[..], b"Content-Length: -0\r\n", b"Content-Length: 4, +4\r\n"] {
+            let mut reader = MessageReader::request();
+            reader.push(&[&b"POST / HTTP/1.1\r\n"[..], head, b"\r\nAAAA"].concat());
+            assert_eq!(reader.framing(), Framing::Invalid);
+        }
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nContent-Length: 4 , 4 \r\n\r\nAAAA");
+        assert_eq!(reader.framing(), Framing::Complete(47));
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n+4\r\nAAAA\r\n0\r\n\r\n");
+        assert_eq!(reader.framing(), Framing::Invalid);
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n-0\r\n\r\n");
+        assert_eq!(reader.framing(), Framing::Invalid);
+    }
+
+    #[test]
+    fn long_head() {
+        // The end of the head is found when split across pushes.
+        let mut reader = MessageReader::request();
+        reader.push(b"GET / HTTP/1.1\r\nHost: a\r\n\r");
+        assert_eq!(reader.framing(), Framing::Incomplete);
+        reader.push(b"\n");
+        assert_eq!(reader.framing(), Framing::Complete(27));
+
+        let mut reader = MessageReader::request();
+        reader.push(b"GET / HTTP/1.1\r\n");
+        for _ in 1..MAX_HEAD_LENGTH / 0x100 {
+            reader.push(&[b'A'; 0x100]);
+            assert_eq!(reader.framing(), Framing::Incomplete);
+        }
+        reader.push(&[b'A'; 0x100]);
+        assert_eq!(reader.framing(), Framing::Invalid);
+    }
+
+    #[test]
+    fn chunk_without_crlf() {
+        let mut reader = MessageReader::request();
+        reader.push(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nAAAAAA\r\n0\r\n\r\n");
+        assert_eq!(reader.framing(), Framing::Invalid);
+    }
+}
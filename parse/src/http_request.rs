@@ -1,5 +1,7 @@
+use crate::error::{ParseError, Reason};
+
 // RFC 9112 - HTTP/1.1
-#[derive(Debug,PartialEq)]
+#[derive(Clone,Debug,PartialEq)]
 pub struct HTTPRequest {
     method: String,
     target: String,
@@ -9,44 +11,153 @@ pub struct HTTPRequest {
 }
 
 impl HTTPRequest {
-    pub fn new(data: &[u8]) -> Option<Self> {
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        if data.is_empty() {
+            return Err(ParseError::new(0, "request-line", Reason::Truncated));
+        }
         // Split on \n. Trim optional trailing \r.
         let mut lines = data.split(|&b| b == b'\n');
+        // Bytes of the head read so far, including line endings.
+        let mut consumed = 0;
         // Read request line
-        if let Ok(request_line) = std::str::from_utf8(lines.next()?) {
-            let mut request_line = request_line.trim().split(' ');
-            let (method, target, version) = (request_line.next()?,
-                                            request_line.next()?,
-                                            request_line.next()?);
-            // Read field lines
-            let mut headers: Vec<(String, String)> = Vec::new();
-            while let Some(header) = lines.next() {
-                if header == [b'\r'] || header.len() == 0 {
-                    break;
-                }
-                let mut header = header.splitn(2, |&b| b == b':');
-                if let (Ok(name), Ok(value)) = (std::str::from_utf8(header.next()?), std::str::from_utf8(header.next()?)) {
-                    headers.push((String::from(name.trim()),
-                                    String::from(value.trim())));
-                } else {
-                    return None;
-                }
+        let request_line = lines.next().unwrap_or(&[]);
+        consumed += request_line.len() + 1;
+        let request_line = utf8(request_line, 0, "request-line")?.trim();
+        let mut parts = request_line.split(' ');
+        let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) => (method, target, version),
+            _ => return Err(ParseError::new(request_line.len(), "request-line", Reason::Malformed)),
+        };
+        // RFC 9112 3 - Exactly three parts. Anything after the version would be lost when the
+        // request is written again.
+        if parts.next().is_some() {
+            return Err(ParseError::new(method.len() + target.len() + version.len() + 3, "request-line", Reason::Malformed));
+        }
+
+        // Read field lines
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for header in lines.by_ref() {
+            let offset = consumed;
+            consumed += header.len() + 1;
+            if header == [b'\r'] || header.is_empty() {
+                break;
             }
+            let header = utf8(header, offset, "field-line")?;
+            // RFC 9112 5.1 - No whitespace is allowed between the field name and the colon, as
+            // peers that ignore it would read a different field.
+            match header.split_once(':') {
+                Some((name, value)) if !name.is_empty() && name == name.trim() => headers.push((String::from(name), String::from(value.trim()))),
+                Some((name, _)) => return Err(ParseError::new(offset + name.len(), "field-name", Reason::Malformed)),
+                None => return Err(ParseError::new(offset, "field-line", Reason::Malformed)),
+            };
+        }
+
+        // Read message body. Everything following the empty line is kept as received.
+        let body = data.get(consumed..).unwrap_or(&[]).to_vec();
+
+        return Ok(HTTPRequest {
+            method: String::from(method),
+            target: String::from(target),
+            version: String::from(version),
+            headers: headers,
+            body: body,
+        });
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
 
-            // Read message body
-            let body = lines.flat_map(|line| line.to_vec())
-                            .collect::<Vec<u8>>();
-
-            return Some(HTTPRequest {
-                method: String::from(method),
-                target: String::from(target),
-                version: String::from(version),
-                headers: headers,
-                body: body,
-            });
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    // Field names are case-insensitive. Returns the value of the first matching field line.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn set_method(&mut self, method: &str) {
+        self.method = String::from(method);
+    }
+
+    pub fn set_target(&mut self, target: &str) {
+        self.target = String::from(target);
+    }
+
+    pub fn set_version(&mut self, version: &str) {
+        self.version = String::from(version);
+    }
+
+    // Replace the value of the first field line with the name, removing any later ones, or add a
+    // field line if there is none. The casing of an existing name is kept.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter().position(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some(first) => {
+                self.headers[first].1 = String::from(value);
+                let later = self.headers.split_off(first + 1);
+                self.headers.extend(later.into_iter().filter(|(key, _)| !key.eq_ignore_ascii_case(name)));
+            },
+            None => self.add_header(name, value),
+        };
+    }
+
+    // Add a field line after the others, even if one with the name exists.
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.push((String::from(name), String::from(value)));
+    }
+
+    // Remove every field line with the name. Returns false if there were none.
+    pub fn remove_header(&mut self, name: &str) -> bool {
+        let count = self.headers.len();
+        self.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        return self.headers.len() != count;
+    }
+
+    pub fn set_body(&mut self, body: Vec<u8>) {
+        self.body = body;
+    }
+
+    // RFC 9112 6.3 - Set Content-Length to the length of the body if the request has the field or
+    // a body. Chunked bodies are delimited by their encoding instead.
+    pub fn update_content_length(&mut self) {
+        let chunked = self.header("Transfer-Encoding")
+            .map(|value| value.rsplit(',').next().unwrap_or("").trim().eq_ignore_ascii_case("chunked"))
+            .unwrap_or(false);
+        if chunked {
+            return;
+        }
+        if self.header("Content-Length").is_some() || !self.body.is_empty() {
+            self.set_header("Content-Length", &self.body.len().to_string());
+        }
+    }
+
+    // Rebuild the request with CRLF line endings and field lines in their original order and
+    // casing. Requests written with a single space after each colon are re-emitted byte for byte.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("{} {} {}\r\n", self.method, self.target, self.version);
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
         }
+        head.push_str("\r\n");
 
-        return None;
+        let mut data = head.into_bytes();
+        data.extend_from_slice(&self.body);
+        return data;
     }
 
     pub fn properties(&self) -> Vec<(String, String)> {
@@ -67,6 +178,11 @@ impl HTTPRequest {
     }
 }
 
+// Decode the bytes found at the offset as UTF-8, reporting the first invalid byte.
+fn utf8<'a>(bytes: &'a [u8], offset: usize, field: &'static str) -> Result<&'a str, ParseError> {
+    std::str::from_utf8(bytes).map_err(|error| ParseError::new(offset + error.valid_up_to(), field, Reason::InvalidUtf8))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::HTTPRequest;
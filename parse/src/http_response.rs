@@ -0,0 +1,192 @@
+// RFC 9112 - HTTP/1.1
+#[derive(Debug,PartialEq)]
+pub struct HTTPResponse {
+    version: String,
+    status_code: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HTTPResponse {
+    pub fn new(data: &[u8]) -> Option<Self> {
+        let head_length = data.windows(4).position(|window| window == b"\r\n\r\n")? + 4;
+        let head = std::str::from_utf8(&data[..head_length]).ok()?;
+        // Split on \n. Trim optional trailing \r.
+        let mut lines = head.split('\n').map(|line| line.trim_end_matches('\r'));
+
+        // Read status line. The reason phrase may contain spaces or be empty.
+        let mut status_line = lines.next()?.splitn(3, ' ');
+        let version = status_line.next()?;
+        if !version.starts_with("HTTP/") {
+            return None;
+        }
+        let status_code = status_line.next()?.parse::<u16>().ok()?;
+        let reason = status_line.next().unwrap_or("");
+
+        // Read field lines
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for header in lines {
+            if header.is_empty() {
+                break;
+            }
+            let (name, value) = header.split_once(':')?;
+            headers.push((String::from(name.trim()), String::from(value.trim())));
+        }
+
+        let mut response = HTTPResponse {
+            version: String::from(version),
+            status_code: status_code,
+            reason: String::from(reason),
+            headers: headers,
+            body: Vec::new(),
+        };
+        response.body = response.read_body(&data[head_length..])?;
+
+        return Some(response);
+    }
+
+    // RFC 9112 6.3 - Determine the message body length from the status code and header fields.
+    fn read_body(&self, data: &[u8]) -> Option<Vec<u8>> {
+        // Informational, No Content and Not Modified responses never have a body.
+        if self.status_code < 200 || self.status_code == 204 || self.status_code == 304 {
+            return Some(Vec::new());
+        }
+
+        let chunked = self.header("Transfer-Encoding")
+            .map(|value| value.rsplit(',').next().unwrap_or("").trim().eq_ignore_ascii_case("chunked"))
+            .unwrap_or(false);
+        if chunked {
+            return decode_chunked(data).map(|(body, _)| body);
+        }
+
+        if let Some(content_length) = self.header("Content-Length") {
+            let content_length = content_length.parse::<usize>().ok()?;
+            // A truncated capture keeps the bytes that were received.
+            return Some(data[..content_length.min(data.len())].to_vec());
+        }
+
+        // The body is delimited by the server closing the connection.
+        return Some(data.to_vec());
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn status_code(&self) -> u16 {
+        self.status_code
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    // Field names are case-insensitive. Returns the value of the first matching field line.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties: Vec<(String, String)> = Vec::<(String, String)>::new();
+        properties.push((String::from("version"), self.version.clone()));
+        properties.push((String::from("status"), self.status_code.to_string()));
+        properties.push((String::from("reason"), self.reason.clone()));
+
+        for (name, value) in self.headers.iter() {
+            properties.push((name.clone(), value.clone()));
+        }
+
+        let mut body_length = self.body.len().to_string();
+        body_length.push_str(" bytes");
+        properties.push((String::from("message body length"), body_length));
+
+        return properties;
+    }
+}
+
+// RFC 9112 7.1 - Decode a chunked message body. Returns the decoded body and the number of bytes
+// consumed, including the trailer section, or None if the body is incomplete or malformed.
+pub fn decode_chunked(data: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut body = Vec::new();
+    let mut i = 0;
+    loop {
+        let line_length = data[i..].windows(2).position(|window| window == b"\r\n")?;
+        let line = std::str::from_utf8(&data[i..i + line_length]).ok()?;
+        // Chunk extensions follow the size and are ignored.
+        let size = line.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        i += line_length + 2;
+
+        if size == 0 {
+            // Skip trailer fields up to the empty line ending the message.
+            loop {
+                let line_length = data[i..].windows(2).position(|window| window == b"\r\n")?;
+                i += line_length + 2;
+                if line_length == 0 {
+                    return Some((body, i));
+                }
+            }
+        }
+
+        body.extend_from_slice(data.get(i..i.checked_add(size)?)?);
+        i += size;
+        if data.get(i..i + 2)? != b"\r\n" {
+            return None;
+        }
+        i += 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http_response::HTTPResponse;
+
+    #[test]
+    fn http_content_length_response() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nAAAABBBB";
+        let parsed = HTTPResponse::new(response.as_bytes()).unwrap();
+        let expected = HTTPResponse {
+            version: String::from("HTTP/1.1"),
+            status_code: 200,
+            reason: String::from("OK"),
+            headers: vec![(String::from("Content-Length"), String::from("4"))],
+            body: "AAAA".as_bytes().to_vec(),
+        };
+        assert_eq!(parsed, expected)
+    }
+
+    #[test]
+    fn http_chunked_response() {
+        let response = "HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nAAAA\r\nA\r\n0123456789\r\n0\r\nTrailer: value\r\n\r\n";
+        let parsed = HTTPResponse::new(response.as_bytes()).unwrap();
+        assert_eq!(parsed.status_code(), 404);
+        assert_eq!(parsed.reason(), "Not Found");
+        assert_eq!(parsed.body(), "AAAA0123456789".as_bytes());
+    }
+
+    #[test]
+    fn http_close_delimited_response() {
+        let response = "HTTP/1.0 200 \r\nServer: test\r\n\r\nAAAA\nBBBB";
+        let parsed = HTTPResponse::new(response.as_bytes()).unwrap();
+        assert_eq!(parsed.reason(), "");
+        assert_eq!(parsed.body(), "AAAA\nBBBB".as_bytes());
+    }
+
+    #[test]
+    fn http_not_modified_response() {
+        let response = "HTTP/1.1 304 Not Modified\r\nContent-Length: 4\r\n\r\n";
+        let parsed = HTTPResponse::new(response.as_bytes()).unwrap();
+        assert_eq!(parsed.body(), "".as_bytes());
+    }
+}
@@ -1,15 +1,30 @@
-mod http_request;
+mod cursor;
+mod der;
+mod writer;
+pub mod diff;
+pub mod error;
+pub mod fingerprint;
+pub mod flow;
+pub mod http_edit;
+pub mod http_request;
+pub mod http_response;
+pub mod http_reader;
 pub mod tls_request;
+pub mod tls_alert;
 pub mod tls_cipher_suite;
 pub mod tls_extension;
+pub mod x509;
 
 use std::fmt;
 
 use tui::draw;
 use tui::Encoding;
+use crate::error::ParseError;
 use crate::http_request::HTTPRequest;
-use crate::tls_request::Record;
+use crate::http_response::HTTPResponse;
+use crate::tls_request::RecordReader;
 
+#[derive(Clone)]
 pub struct Request {
     timestamp: u128,
     data: Vec<u8>,
@@ -22,6 +37,10 @@ impl Request {
             data: data,
         }
     }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
 }
 
 impl fmt::Display for Request {
@@ -45,9 +64,9 @@ impl draw::LogEntry for Request {
 
     fn to_lines(&self, encoding: &Encoding) -> Vec<String> {
         let body: Vec<String> = match encoding {
-            Encoding::Protocol(protocol) => self.parse_protocol(&protocol),
+            Encoding::Protocol(protocol) => self.parse_protocol(protocol),
             Encoding::Text => match std::str::from_utf8(&(self.data)) {
-                        Ok(s) => s.split("\r\n").map(|s| String::from(s)).collect(),
+                        Ok(s) => s.split("\r\n").map(String::from).collect(),
                         // TODO: Do this without calling collect twice.
                         Err(_) => (self.data.iter().map(|v| format!("{:02X} ", v))
                                     .collect::<Vec<_>>())
@@ -80,30 +99,63 @@ impl Request {
     fn parse_protocol(&self, protocol: &str) -> Vec<String> {
         match protocol {
             "http" => {
-                if let Some(http) = HTTPRequest::new(&self.data[..]) {
-                    let mut lines: Vec<String> = Vec::new();
-                    for (key, val) in http.properties().iter() {
-                        lines.push(format!("{}: {}", key.to_owned(), val));
+                // A request line never starts with the HTTP version.
+                if self.data.starts_with(b"HTTP/") {
+                    if let Some(http) = HTTPResponse::new(&self.data[..]) {
+                        let mut lines: Vec<String> = Vec::new();
+                        for (key, val) in http.properties().iter() {
+                            lines.push(format!("{}: {}", key.to_owned(), val));
+                        }
+                        return lines;
+                    } else {
+                        return vec![String::from("ERROR: Parsing failed.")];
                     }
-                    return lines;
-                } else {
-                    return vec![String::from("ERROR: Parsing failed.")];
+                }
+                match HTTPRequest::new(&self.data[..]) {
+                    Ok(http) => {
+                        let mut lines: Vec<String> = Vec::new();
+                        for (key, val) in http.properties().iter() {
+                            lines.push(format!("{}: {}", key.to_owned(), val));
+                        }
+                        return lines;
+                    },
+                    Err(error) => return self.error_lines(&error),
                 }
             },
             "tls" => {
-                if let Some(tls) = Record::new(&self.data[..]) {
-                    let mut lines: Vec<String> = Vec::new();
-                    for (key, val) in tls.properties().iter() {
-                        lines.push(format!("{}: {}", key.to_owned(), val));
-                    }
-                    return lines;
-                } else {
-                    return vec![String::from("ERROR: Parsing failed.")];
+                // Every record in the capture, with the messages each one completes.
+                let mut lines: Vec<String> = Vec::new();
+                for record in RecordReader::new(&self.data[..]) {
+                    match record {
+                        Ok(record) => {
+                            for (key, val) in record.properties().iter() {
+                                lines.push(format!("{}: {}", key.to_owned(), val));
+                            }
+                        },
+                        Err(error) => {
+                            lines.extend(self.error_lines(&error));
+                            break;
+                        },
+                    };
                 }
+                return lines;
             },
             _ => {
                 vec![String::from("ERROR: Unknown protocol ") + protocol]
             },
         }
     }
+
+    // Describe the error and show the bytes around it, marking the byte where parsing failed.
+    fn error_lines(&self, error: &ParseError) -> Vec<String> {
+        let start = error.offset.saturating_sub(8);
+        let end = (error.offset + 8).min(self.data.len());
+        let mut bytes: Vec<String> = (start..end)
+            .map(|i| if i == error.offset { format!("[{:02X}]", self.data[i]) } else { format!("{:02X}", self.data[i]) })
+            .collect();
+        if error.offset >= self.data.len() {
+            bytes.push(String::from("[end]"));
+        }
+        return vec![format!("ERROR: Parsing failed: {}", error), format!("{:>6}: {}", start, bytes.join(" "))];
+    }
 }
@@ -0,0 +1,281 @@
+use std::fmt;
+
+use crate::cursor::Cursor;
+use crate::error::ParseError;
+use crate::tls_request::{Record, RecordContentType};
+
+// RFC 8446 6 - Sent to close the connection, or to report the error that made the sender abort
+// the handshake.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Alert {
+    pub level: AlertLevel,
+    pub description: AlertDescription,
+}
+
+impl Alert {
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        Self::read(&mut Cursor::new(data))
+    }
+
+    // The alert in the first record of the capture, if it starts with an alert record in the
+    // clear.
+    pub fn from_capture(data: &[u8]) -> Option<Self> {
+        if data.first() != Some(&RecordContentType::Alert.id()) {
+            return None;
+        }
+        return Record::new(data).ok()?.alert;
+    }
+
+    // Whether the fragment of an alert record holds alerts in the clear: pairs of bytes, each
+    // starting with a known level. TLS 1.2 alerts encrypted after a change of cipher spec keep the
+    // alert content type.
+    pub(crate) fn is_plaintext(fragment: &[u8]) -> bool {
+        return !fragment.is_empty() && fragment.len().is_multiple_of(2)
+            && fragment.chunks(2).all(|alert| AlertLevel::new(alert[0]) != AlertLevel::Missing(alert[0]));
+    }
+
+    pub(crate) fn read(data: &mut Cursor) -> Result<Self, ParseError> {
+        let level = AlertLevel::new(data.u8("level")?);
+        let description = AlertDescription::new(data.u8("description")?);
+        return Ok(Alert {
+            level: level,
+            description: description,
+        });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        vec![self.level.id(), self.description.id()]
+    }
+}
+
+// As "fatal: handshake_failure".
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}: {}", self.level, self.description)
+    }
+}
+
+// TLS 1.3 ignores the level of alerts other than close_notify and user_canceled, which are
+// warnings, and treats the others as fatal.
+#[derive(Clone,Debug,PartialEq)]
+pub enum AlertLevel {
+    Warning,
+    Fatal,
+    Missing(u8),
+}
+
+impl AlertLevel {
+    pub fn new(n: u8) -> Self {
+        match n {
+            1 => AlertLevel::Warning,
+            2 => AlertLevel::Fatal,
+            _ => AlertLevel::Missing(n),
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            AlertLevel::Warning => 1,
+            AlertLevel::Fatal => 2,
+            AlertLevel::Missing(n) => *n,
+        }
+    }
+}
+
+impl fmt::Display for AlertLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            AlertLevel::Warning => write!(f, "warning"),
+            AlertLevel::Fatal => write!(f, "fatal"),
+            AlertLevel::Missing(n) => write!(f, "level {}", n),
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum AlertDescription {
+    // RFC 8446 TLS 1.3
+    CloseNotify,
+    UnexpectedMessage,
+    BadRecordMAC,
+    RecordOverflow,
+    HandshakeFailure,
+    BadCertificate,
+    UnsupportedCertificate,
+    CertificateRevoked,
+    CertificateExpired,
+    CertificateUnknown,
+    IllegalParameter,
+    UnknownCA,
+    AccessDenied,
+    DecodeError,
+    DecryptError,
+    ProtocolVersion,
+    InsufficientSecurity,
+    InternalError,
+    InappropriateFallback,
+    UserCanceled,
+    MissingExtension,
+    UnsupportedExtension,
+    UnrecognizedName,
+    BadCertificateStatusResponse,
+    UnknownPSKIdentity,
+    CertificateRequired,
+    NoApplicationProtocol,
+    // Reserved by TLS 1.3 for alerts of earlier versions.
+    DecryptionFailed,
+    DecompressionFailure,
+    NoCertificate,
+    ExportRestriction,
+    NoRenegotiation,
+    CertificateUnobtainable,
+    BadCertificateHashValue,
+    Missing(u8),
+}
+
+impl AlertDescription {
+    pub fn new(n: u8) -> Self {
+        match n {
+            0 => AlertDescription::CloseNotify,
+            10 => AlertDescription::UnexpectedMessage,
+            20 => AlertDescription::BadRecordMAC,
+            // RFC 5246 TLS 1.2
+            21 => AlertDescription::DecryptionFailed,
+            22 => AlertDescription::RecordOverflow,
+            // RFC 5246 TLS 1.2
+            30 => AlertDescription::DecompressionFailure,
+            40 => AlertDescription::HandshakeFailure,
+            // SSL 3.0
+            41 => AlertDescription::NoCertificate,
+            42 => AlertDescription::BadCertificate,
+            43 => AlertDescription::UnsupportedCertificate,
+            44 => AlertDescription::CertificateRevoked,
+            45 => AlertDescription::CertificateExpired,
+            46 => AlertDescription::CertificateUnknown,
+            47 => AlertDescription::IllegalParameter,
+            48 => AlertDescription::UnknownCA,
+            49 => AlertDescription::AccessDenied,
+            50 => AlertDescription::DecodeError,
+            51 => AlertDescription::DecryptError,
+            // RFC 2246 TLS 1.0
+            60 => AlertDescription::ExportRestriction,
+            70 => AlertDescription::ProtocolVersion,
+            71 => AlertDescription::InsufficientSecurity,
+            80 => AlertDescription::InternalError,
+            86 => AlertDescription::InappropriateFallback,
+            90 => AlertDescription::UserCanceled,
+            // RFC 5246 TLS 1.2
+            100 => AlertDescription::NoRenegotiation,
+            109 => AlertDescription::MissingExtension,
+            110 => AlertDescription::UnsupportedExtension,
+            // RFC 6066 TLS Extensions
+            111 => AlertDescription::CertificateUnobtainable,
+            112 => AlertDescription::UnrecognizedName,
+            113 => AlertDescription::BadCertificateStatusResponse,
+            114 => AlertDescription::BadCertificateHashValue,
+            115 => AlertDescription::UnknownPSKIdentity,
+            116 => AlertDescription::CertificateRequired,
+            120 => AlertDescription::NoApplicationProtocol,
+            _ => AlertDescription::Missing(n),
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            AlertDescription::CloseNotify => 0,
+            AlertDescription::UnexpectedMessage => 10,
+            AlertDescription::BadRecordMAC => 20,
+            AlertDescription::DecryptionFailed => 21,
+            AlertDescription::RecordOverflow => 22,
+            AlertDescription::DecompressionFailure => 30,
+            AlertDescription::HandshakeFailure => 40,
+            AlertDescription::NoCertificate => 41,
+            AlertDescription::BadCertificate => 42,
+            AlertDescription::UnsupportedCertificate => 43,
+            AlertDescription::CertificateRevoked => 44,
+            AlertDescription::CertificateExpired => 45,
+            AlertDescription::CertificateUnknown => 46,
+            AlertDescription::IllegalParameter => 47,
+            AlertDescription::UnknownCA => 48,
+            AlertDescription::AccessDenied => 49,
+            AlertDescription::DecodeError => 50,
+            AlertDescription::DecryptError => 51,
+            AlertDescription::ExportRestriction => 60,
+            AlertDescription::ProtocolVersion => 70,
+            AlertDescription::InsufficientSecurity => 71,
+            AlertDescription::InternalError => 80,
+            AlertDescription::InappropriateFallback => 86,
+            AlertDescription::UserCanceled => 90,
+            AlertDescription::NoRenegotiation => 100,
+            AlertDescription::MissingExtension => 109,
+            AlertDescription::UnsupportedExtension => 110,
+            AlertDescription::CertificateUnobtainable => 111,
+            AlertDescription::UnrecognizedName => 112,
+            AlertDescription::BadCertificateStatusResponse => 113,
+            AlertDescription::BadCertificateHashValue => 114,
+            AlertDescription::UnknownPSKIdentity => 115,
+            AlertDescription::CertificateRequired => 116,
+            AlertDescription::NoApplicationProtocol => 120,
+            AlertDescription::Missing(n) => *n,
+        }
+    }
+}
+
+// The names used by the specifications, such as "handshake_failure".
+impl fmt::Display for AlertDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let name = match self {
+            AlertDescription::CloseNotify => "close_notify",
+            AlertDescription::UnexpectedMessage => "unexpected_message",
+            AlertDescription::BadRecordMAC => "bad_record_mac",
+            AlertDescription::DecryptionFailed => "decryption_failed",
+            AlertDescription::RecordOverflow => "record_overflow",
+            AlertDescription::DecompressionFailure => "decompression_failure",
+            AlertDescription::HandshakeFailure => "handshake_failure",
+            AlertDescription::NoCertificate => "no_certificate",
+            AlertDescription::BadCertificate => "bad_certificate",
+            AlertDescription::UnsupportedCertificate => "unsupported_certificate",
+            AlertDescription::CertificateRevoked => "certificate_revoked",
+            AlertDescription::CertificateExpired => "certificate_expired",
+            AlertDescription::CertificateUnknown => "certificate_unknown",
+            AlertDescription::IllegalParameter => "illegal_parameter",
+            AlertDescription::UnknownCA => "unknown_ca",
+            AlertDescription::AccessDenied => "access_denied",
+            AlertDescription::DecodeError => "decode_error",
+            AlertDescription::DecryptError => "decrypt_error",
+            AlertDescription::ExportRestriction => "export_restriction",
+            AlertDescription::ProtocolVersion => "protocol_version",
+            AlertDescription::InsufficientSecurity => "insufficient_security",
+            AlertDescription::InternalError => "internal_error",
+            AlertDescription::InappropriateFallback => "inappropriate_fallback",
+            AlertDescription::UserCanceled => "user_canceled",
+            AlertDescription::NoRenegotiation => "no_renegotiation",
+            AlertDescription::MissingExtension => "missing_extension",
+            AlertDescription::UnsupportedExtension => "unsupported_extension",
+            AlertDescription::CertificateUnobtainable => "certificate_unobtainable",
+            AlertDescription::UnrecognizedName => "unrecognized_name",
+            AlertDescription::BadCertificateStatusResponse => "bad_certificate_status_response",
+            AlertDescription::BadCertificateHashValue => "bad_certificate_hash_value",
+            AlertDescription::UnknownPSKIdentity => "unknown_psk_identity",
+            AlertDescription::CertificateRequired => "certificate_required",
+            AlertDescription::NoApplicationProtocol => "no_application_protocol",
+            AlertDescription::Missing(n) => return write!(f, "unknown alert {}", n),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions_round_trip() {
+        for n in 0..=u8::MAX {
+            assert_eq!(AlertDescription::new(n).id(), n);
+        }
+        assert_eq!(Alert::new(&[0x02, 0x28]).unwrap().to_string(), "fatal: handshake_failure");
+        assert_eq!(Alert::new(&[0x01, 0x00]).unwrap().to_string(), "warning: close_notify");
+        assert_eq!(Alert::new(&[0x05, 0xC8]).unwrap().to_string(), "level 5: unknown alert 200");
+    }
+}
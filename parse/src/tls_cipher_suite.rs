@@ -1,3 +1,4 @@
+use crate::tls_extension::is_grease;
 
 #[derive(Debug,PartialEq)]
 pub enum CipherSuite {
@@ -256,6 +257,8 @@ pub enum CipherSuite {
     TLSChaCha20Poly1305SHA256,
     TLSAES128CCMSHA256,
     TLSAES128CCM8SHA256,
+    // RFC 8701 - Reserved values sent to keep servers tolerant of unknown cipher suites.
+    GREASE(u8, u8),
     UNKNOWN(u8, u8),
 }
 
@@ -528,7 +531,266 @@ impl CipherSuite {
                 0x05 => CipherSuite::TLSAES128CCM8SHA256,
                 _ => CipherSuite::UNKNOWN(b0, b1),
             },
+            _ if is_grease(u16::from_be_bytes([b0, b1])) => CipherSuite::GREASE(b0, b1),
             _ => CipherSuite::UNKNOWN(b0, b1),
         }
     }
+
+    // The value identifying the cipher suite on the wire.
+    pub fn id(&self) -> u16 {
+        match self {
+            CipherSuite::TLSRSAWithNullNull => 0x0000,
+            CipherSuite::TLSRSAWithNullMD5 => 0x0001,
+            CipherSuite::TLSRSAWithNullSHA => 0x0002,
+            CipherSuite::TLSRSAWithNullSHA256 => 0x003B,
+            CipherSuite::TLSRSAWithRC4128MD5 => 0x0004,
+            CipherSuite::TLSRSAWithRC4128SHA => 0x0005,
+            CipherSuite::TLSRSAWith3DESEDECBCSHA => 0x000A,
+            CipherSuite::TLSRSAWithAES128CBCSHA => 0x002F,
+            CipherSuite::TLSRSAWithAES256CBCSHA => 0x0035,
+            CipherSuite::TLSRSAWithAES128CBCSHA256 => 0x003C,
+            CipherSuite::TLSRSAWithAES256CBCSHA256 => 0x003D,
+            CipherSuite::TLSDHDSSWith3DESEDECBCSHA => 0x000D,
+            CipherSuite::TLSDHRSAWith3DESEDECBCSHA => 0x0010,
+            CipherSuite::TLSDHEDSSWith3DESEDECBCSHA => 0x0013,
+            CipherSuite::TLSDHERSAWith3DESEDECBCSHA => 0x0016,
+            CipherSuite::TLSDHDSSWithAES128CBCSHA => 0x0030,
+            CipherSuite::TLSDHRSAWithAES128CBCSHA => 0x0031,
+            CipherSuite::TLSDHEDSSWithAES128CBCSHA => 0x0032,
+            CipherSuite::TLSDHERSAWithAES128CBCSHA => 0x0033,
+            CipherSuite::TLSDHDSSWithAES256CBCSHA => 0x0036,
+            CipherSuite::TLSDHRSAWithAES256CBCSHA => 0x0037,
+            CipherSuite::TLSDHEDSSWithAES256CBCSHA => 0x0038,
+            CipherSuite::TLSDHERSAWithAES256CBCSHA => 0x0039,
+            CipherSuite::TLSDHDSSWithAES128CBCSHA256 => 0x003E,
+            CipherSuite::TLSDHRSAWithAES128CBCSHA256 => 0x003F,
+            CipherSuite::TLSDHEDSSWithAES128CBCSHA256 => 0x0040,
+            CipherSuite::TLSDHERSAWithAES128CBCSHA256 => 0x0067,
+            CipherSuite::TLSDHDSSWithAES256CBCSHA256 => 0x0068,
+            CipherSuite::TLSDHRSAWithAES256CBCSHA256 => 0x0069,
+            CipherSuite::TLSDHEDSSWithAES256CBCSHA256 => 0x006A,
+            CipherSuite::TLSDHERSAWithAES256CBCSHA256 => 0x006B,
+            CipherSuite::TLSDHAnonWithRC4128MD5 => 0x0018,
+            CipherSuite::TLSDHAnonWith3DESEDECBCSHA => 0x001B,
+            CipherSuite::TLSDHAnonWithAES128CBCSHA => 0x0034,
+            CipherSuite::TLSDHAnonWithAES256CBCSHA => 0x003A,
+            CipherSuite::TLSDHAnonWithAES128CBCSHA256 => 0x006C,
+            CipherSuite::TLSDHAnonWithAES256CBCSHA256 => 0x006D,
+            // RFC 4162 Addition of SEED Cipher Suites to TLS
+            CipherSuite::TLSRSAWithSEEDCBCSHA => 0x0096,
+            CipherSuite::TLSDHDSSWithSEEDCBCSHA => 0x0097,
+            CipherSuite::TLSDHRSAWithSEEDCBCSHA => 0x0098,
+            CipherSuite::TLSDHEDSSWithSEEDCBCSHA => 0x0099,
+            CipherSuite::TLSDHERSAWithSEEDCBCSHA => 0x009A,
+            CipherSuite::TLSDHAnonWithSEEDCBCSHA => 0x009B,
+            // RFC 5288 AES GCM Cipher Suites for TLS
+            CipherSuite::TLSRSAWithAES128GCMSHA256 => 0x009C,
+            CipherSuite::TLSRSAWithAES256GCMSHA384 => 0x009D,
+            CipherSuite::TLSDHERSAWithAES128GCMSHA256 => 0x009E,
+            CipherSuite::TLSDHERSAWithAES256GCMSHA384 => 0x009F,
+            CipherSuite::TLSDHRSAWithAES128GCMSHA256 => 0x00A0,
+            CipherSuite::TLSDHRSAWithAES256GCMSHA384 => 0x00A1,
+            CipherSuite::TLSDHEDSSWithAES128GCMSHA256 => 0x00A2,
+            CipherSuite::TLSDHEDSSWithAES256GCMSHA384 => 0x00A3,
+            CipherSuite::TLSDHDSSWithAES128GCMSHA256 => 0x00A4,
+            CipherSuite::TLSDHDSSWithAES256GCMSHA384 => 0x00A5,
+            CipherSuite::TLSDHanonWithAES128GCMSHA256 => 0x00A6,
+            CipherSuite::TLSDHanonWithAES256GCMSHA384 => 0x00A7,
+            // RFC 5932 Camellia Cipher Suites for TLS
+            CipherSuite::TLSRSAWithCamellia128CBCSHA => 0x0041,
+            CipherSuite::TLSDHDSSWithCamellia128CBCSHA => 0x0042,
+            CipherSuite::TLSDHRSAWithCamellia128CBCSHA => 0x0043,
+            CipherSuite::TLSDHEDSSWithCamellia128CBCSHA => 0x0044,
+            CipherSuite::TLSDHERSAWithCamellia128CBCSHA => 0x0045,
+            CipherSuite::TLSDHanonWithCamellia128CBCSHA => 0x0046,
+            CipherSuite::TLSRSAWithCamellia256CBCSHA => 0x0084,
+            CipherSuite::TLSDHDSSWithCamellia256CBCSHA => 0x0085,
+            CipherSuite::TLSDHRSAWithCamellia256CBCSHA => 0x0086,
+            CipherSuite::TLSDHEDSSWithCamellia256CBCSHA => 0x0087,
+            CipherSuite::TLSDHERSAWithCamellia256CBCSHA => 0x0088,
+            CipherSuite::TLSDHanonWithCamellia256CBCSHA => 0x0089,
+            CipherSuite::TLSRSAWithCamellia128CBCSHA256 => 0x00BA,
+            CipherSuite::TLSDHDSSWithCamellia128CBCSHA256 => 0x00BB,
+            CipherSuite::TLSDHRSAWithCamellia128CBCSHA256 => 0x00BC,
+            CipherSuite::TLSDHEDSSWithCamellia128CBCSHA256 => 0x00BD,
+            CipherSuite::TLSDHERSAWithCamellia128CBCSHA256 => 0x00BE,
+            CipherSuite::TLSDHanonWithCamellia128CBCSHA256 => 0x00BF,
+            CipherSuite::TLSRSAWithCamellia256CBCSHA256 => 0x00C0,
+            CipherSuite::TLSDHDSSWithCamellia256CBCSHA256 => 0x00C1,
+            CipherSuite::TLSDHRSAWithCamellia256CBCSHA256 => 0x00C2,
+            CipherSuite::TLSDHEDSSWithCamellia256CBCSHA256 => 0x00C3,
+            CipherSuite::TLSDHERSAWithCamellia256CBCSHA256 => 0x00C4,
+            CipherSuite::TLSDHanonWithCamellia256CBCSHA256 => 0x00C5,
+            // RFC 5746 TLS Renegotiation Indication Extension
+            CipherSuite::TLSEmptyRenegotiationInfoSCSV => 0x00FF,
+            // RFC 8442 ECDHE_PSK with AES-GCM and AES-CCM Cipher Suites for TLS 1.2
+            CipherSuite::TLSECDHEPSKWithAES128GCMSHA256 => 0xD001,
+            CipherSuite::TLSECDHEPSKWithAES256GCMSHA384 => 0xD002,
+            CipherSuite::TLSECDHEPSKWithAES128CCM8SHA256 => 0xD003,
+            CipherSuite::TLSECDHEPSKWithAES128CCMSHA256 => 0xD005,
+            // RFC 5289 TLS Elliptic Curve Cipher Suites with SHA-256/384 and AES GCM
+            CipherSuite::TLSECDHEECDSAWithAES128CBCSHA256 => 0xC023,
+            CipherSuite::TLSECDHEECDSAWithAES256CBCSHA384 => 0xC024,
+            CipherSuite::TLSECDHECDSAWithAES128CBCSHA256 => 0xC025,
+            CipherSuite::TLSECDHECDSAWithAES256CBCSHA384 => 0xC026,
+            CipherSuite::TLSECDHERSAWithAES128CBCSHA256 => 0xC027,
+            CipherSuite::TLSECDHERSAWithAES256CBCSHA384 => 0xC028,
+            CipherSuite::TLSECDHRSAWithAES128CBCSHA256 => 0xC029,
+            CipherSuite::TLSECDHRSAWithAES256CBCSHA384 => 0xC02A,
+            CipherSuite::TLSECDHECDSAWithAES128GCMSHA256 => 0xC02D,
+            CipherSuite::TLSECDHECDSAWithAES256GCMSHA384 => 0xC02E,
+            CipherSuite::TLSECDHRSAWithAES128GCMSHA256 => 0xC031,
+            CipherSuite::TLSECDHRSAWithAES256GCMSHA384 => 0xC032,
+            // RFC 6209 Addition of the ARIA Cipher Suites to TLS
+            CipherSuite::TLSRSAWithARIA128CBCSHA256 => 0xC03C,
+            CipherSuite::TLSRSAWithARIA256CBCSHA384 => 0xC03D,
+            CipherSuite::TLSDHDSSWithARIA128CBCSHA256 => 0xC03E,
+            CipherSuite::TLSDHDSSWithARIA256CBCSHA384 => 0xC03F,
+            CipherSuite::TLSDHRSAWithARIA128CBCSHA256 => 0xC040,
+            CipherSuite::TLSDHRSAWithARIA256CBCSHA384 => 0xC041,
+            CipherSuite::TLSDHEDSSWithARIA128CBCSHA256 => 0xC042,
+            CipherSuite::TLSDHEDSSWithARIA256CBCSHA384 => 0xC043,
+            CipherSuite::TLSDHERSAWithARIA128CBCSHA256 => 0xC044,
+            CipherSuite::TLSDHERSAWithARIA256CBCSHA384 => 0xC045,
+            CipherSuite::TLSDHAnonWithARIA128CBCSHA256 => 0xC046,
+            CipherSuite::TLSDHAnonWithARIA256CBCSHA384 => 0xC047,
+            CipherSuite::TLSECDHEECDSAWithARIA128CBCSHA256 => 0xC048,
+            CipherSuite::TLSECDHEECDSAWithARIA256CBCSHA384 => 0xC049,
+            CipherSuite::TLSECDHECDSAWithARIA128CBCSHA256 => 0xC04A,
+            CipherSuite::TLSECDHECDSAWithARIA256CBCSHA384 => 0xC04B,
+            CipherSuite::TLSECDHERSAWithARIA128CBCSHA256 => 0xC04C,
+            CipherSuite::TLSECDHERSAWithARIA256CBCSHA384 => 0xC04D,
+            CipherSuite::TLSECDHRSAWithARIA128CBCSHA256 => 0xC04E,
+            CipherSuite::TLSECDHRSAWithARIA256CBCSHA384 => 0xC04F,
+            CipherSuite::TLSRSAWithARIA128GCMSHA256 => 0xC050,
+            CipherSuite::TLSRSAWithARIA256GCMSHA384 => 0xC051,
+            CipherSuite::TLSDHERSAWithARIA128GCMSHA256 => 0xC052,
+            CipherSuite::TLSDHERSAWithARIA256GCMSHA384 => 0xC053,
+            CipherSuite::TLSDHRSAWithARIA128GCMSHA256 => 0xC054,
+            CipherSuite::TLSDHRSAWithARIA256GCMSHA384 => 0xC055,
+            CipherSuite::TLSDHEDSSWithARIA128GCMSHA256 => 0xC056,
+            CipherSuite::TLSDHEDSSWithARIA256GCMSHA384 => 0xC057,
+            CipherSuite::TLSDHDSSWithARIA128GCMSHA256 => 0xC058,
+            CipherSuite::TLSDHDSSWithARIA256GCMSHA384 => 0xC059,
+            CipherSuite::TLSDHanonWithARIA128GCMSHA256 => 0xC05A,
+            CipherSuite::TLSDHanonWithARIA256GCMSHA384 => 0xC05B,
+            CipherSuite::TLSECDHEECDSAWithARIA128GCMSHA256 => 0xC05C,
+            CipherSuite::TLSECDHEECDSAWithARIA256GCMSHA384 => 0xC05D,
+            CipherSuite::TLSECDHECDSAWithARIA128GCMSHA256 => 0xC05E,
+            CipherSuite::TLSECDHECDSAWithARIA256GCMSHA384 => 0xC05F,
+            CipherSuite::TLSECDHERSAWithARIA128GCMSHA256 => 0xC060,
+            CipherSuite::TLSECDHERSAWithARIA256GCMSHA384 => 0xC061,
+            CipherSuite::TLSECDHRSAWithARIA128GCMSHA256 => 0xC062,
+            CipherSuite::TLSECDHRSAWithARIA256GCMSHA384 => 0xC063,
+            CipherSuite::TLSPSKWithARIA128CBCSHA256 => 0xC064,
+            CipherSuite::TLSPSKWithARIA256CBCSHA384 => 0xC065,
+            CipherSuite::TLSDHEPSKWithARIA128CBCSHA256 => 0xC066,
+            CipherSuite::TLSDHEPSKWithARIA256CBCSHA384 => 0xC067,
+            CipherSuite::TLSRSAPSKWithARIA128CBCSHA256 => 0xC068,
+            CipherSuite::TLSRSAPSKWithARIA256CBCSHA384 => 0xC069,
+            CipherSuite::TLSPSKWithARIA128GCMSHA256 => 0xC06A,
+            CipherSuite::TLSPSKWithARIA256GCMSHA384 => 0xC06B,
+            CipherSuite::TLSDHEPSKWithARIA128GCMSHA256 => 0xC06C,
+            CipherSuite::TLSDHEPSKWithARIA256GCMSHA384 => 0xC06D,
+            CipherSuite::TLSRSAPSKWithARIA128GCMSHA256 => 0xC06E,
+            CipherSuite::TLSRSAPSKWithARIA256GCMSHA384 => 0xC06F,
+            CipherSuite::TLSECDHEPSKWithARIA128CBCSHA256 => 0xC070,
+            CipherSuite::TLSECDHEPSKWithARIA256CBCSHA384 => 0xC071,
+            // RFC 6367 Addition of the Camellia Cipher Suites to TLS
+            CipherSuite::TLSECDHEECDSAWithCamellia128CBCSHA256 => 0xC072,
+            CipherSuite::TLSECDHEECDSAWithCamellia256CBCSHA384 => 0xC073,
+            CipherSuite::TLSECDHECDSAWithCamellia128CBCSHA256 => 0xC074,
+            CipherSuite::TLSECDHECDSAWithCamellia256CBCSHA384 => 0xC075,
+            CipherSuite::TLSECDHERSAWithCamellia128CBCSHA256 => 0xC076,
+            CipherSuite::TLSECDHERSAWithCamellia256CBCSHA384 => 0xC077,
+            CipherSuite::TLSECDHRSAWithCamellia128CBCSHA256 => 0xC078,
+            CipherSuite::TLSECDHRSAWithCamellia256CBCSHA384 => 0xC079,
+            CipherSuite::TLSRSAWithCamellia128GCMSHA256 => 0xC07A,
+            CipherSuite::TLSRSAWithCamellia256GCMSHA384 => 0xC07B,
+            CipherSuite::TLSDHERSAWithCamellia128GCMSHA256 => 0xC07C,
+            CipherSuite::TLSDHERSAWithCamellia256GCMSHA384 => 0xC07D,
+            CipherSuite::TLSDHRSAWithCamellia128GCMSHA256 => 0xC07E,
+            CipherSuite::TLSDHRSAWithCamellia256GCMSHA384 => 0xC07F,
+            CipherSuite::TLSDHEDSSWithCamellia128GCMSHA256 => 0xC080,
+            CipherSuite::TLSDHEDSSWithCamellia256GCMSHA384 => 0xC081,
+            CipherSuite::TLSDHDSSWithCamellia128GCMSHA256 => 0xC082,
+            CipherSuite::TLSDHDSSWithCamellia256GCMSHA384 => 0xC083,
+            CipherSuite::TLSDHanonWithCamellia128GCMSHA256 => 0xC084,
+            CipherSuite::TLSDHanonWithCamellia256GCMSHA384 => 0xC085,
+            CipherSuite::TLSECDHEECDSAWithCamellia128GCMSHA256 => 0xC086,
+            CipherSuite::TLSECDHEECDSAWithCamellia256GCMSHA384 => 0xC087,
+            CipherSuite::TLSECDHECDSAWithCamellia128GCMSHA256 => 0xC088,
+            CipherSuite::TLSECDHECDSAWithCamellia256GCMSHA384 => 0xC089,
+            CipherSuite::TLSECDHERSAWithCamellia128GCMSHA256 => 0xC08A,
+            CipherSuite::TLSECDHERSAWithCamellia256GCMSHA384 => 0xC08B,
+            CipherSuite::TLSECDHRSAWithCamellia128GCMSHA256 => 0xC08C,
+            CipherSuite::TLSECDHRSAWithCamellia256GCMSHA384 => 0xC08D,
+            CipherSuite::TLSPSKWithCamellia128GCMSHA256 => 0xC08E,
+            CipherSuite::TLSPSKWithCamellia256GCMSHA384 => 0xC08F,
+            CipherSuite::TLSDHEPSKWithCamellia128GCMSHA256 => 0xC090,
+            CipherSuite::TLSDHEPSKWithCamellia256GCMSHA384 => 0xC091,
+            CipherSuite::TLSRSAPSKWithCamellia128GCMSHA256 => 0xC092,
+            CipherSuite::TLSRSAPSKWithCamellia256GCMSHA384 => 0xC093,
+            CipherSuite::TLSPSKWithCamellia128CBCSHA256 => 0xC094,
+            CipherSuite::TLSPSKWithCamellia256CBCSHA384 => 0xC095,
+            CipherSuite::TLSDHEPSKWithCamellia128CBCSHA256 => 0xC096,
+            CipherSuite::TLSDHEPSKWithCamellia256CBCSHA384 => 0xC097,
+            CipherSuite::TLSRSAPSKWithCamellia128CBCSHA256 => 0xC098,
+            CipherSuite::TLSRSAPSKWithCamellia256CBCSHA384 => 0xC099,
+            CipherSuite::TLSECDHEPSKWithCamellia128CBCSHA256 => 0xC09A,
+            CipherSuite::TLSECDHEPSKWithCamellia256CBCSHA384 => 0xC09B,
+            // RFC 6655 AES-CCM Cipher Suites for TLS
+            CipherSuite::TLSRSAWithAES128CCM => 0xC09C,
+            CipherSuite::TLSRSAWithAES256CCM => 0xC09D,
+            CipherSuite::TLSDHERSAWithAES128CCM => 0xC09E,
+            CipherSuite::TLSDHERSAWithAES256CCM => 0xC09F,
+            CipherSuite::TLSRSAWithAES128CCM8 => 0xC0A0,
+            CipherSuite::TLSRSAWithAES256CCM8 => 0xC0A1,
+            CipherSuite::TLSDHERSAWithAES128CCM8 => 0xC0A2,
+            CipherSuite::TLSDHERSAWithAES256CCM8 => 0xC0A3,
+            CipherSuite::TLSPSKWithAES128CCM => 0xC0A4,
+            CipherSuite::TLSPSKWithAES256CCM => 0xC0A5,
+            CipherSuite::TLSDHEPSKWithAES128CCM => 0xC0A6,
+            CipherSuite::TLSDHEPSKWithAES256CCM => 0xC0A7,
+            CipherSuite::TLSPSKWithAES128CCM8 => 0xC0A8,
+            CipherSuite::TLSPSKWithAES256CCM8 => 0xC0A9,
+            CipherSuite::TLSPSKDHEWithAES128CCM8 => 0xC0AA,
+            CipherSuite::TLSPSKDHEWithAES256CCM8 => 0xC0AB,
+            // RFC 7251 AES-CCM Elliptic Curve Cryptography Cipher Suites for TLS
+            CipherSuite::TLSECDHEECDSAWithAES128CCM => 0xC0AC,
+            CipherSuite::TLSECDHEECDSAWithAES256CCM => 0xC0AD,
+            CipherSuite::TLSECDHEECDSAWithAES128CCM8 => 0xC0AE,
+            CipherSuite::TLSECDHEECDSAWithAES256CCM8 => 0xC0AF,
+            CipherSuite::TLSECDHEECDSAWithNullSHA => 0xC006,
+            CipherSuite::TLSECDHEECDSAWith3DESEDECBCSHA => 0xC008,
+            CipherSuite::TLSECDHEECDSAWithAES128CBCSHA => 0xC009,
+            CipherSuite::TLSECDHEECDSAWithAES256CBCSHA => 0xC00A,
+            CipherSuite::TLSECDHEECDSAWithAES128GCMSHA256 => 0xC02B,
+            CipherSuite::TLSECDHEECDSAWithAES256GCMSHA384 => 0xC02C,
+            CipherSuite::TLSECDHERSAWithNullSHA => 0xC010,
+            CipherSuite::TLSECDHERSAWith3DESEDECBCSHA => 0xC012,
+            CipherSuite::TLSECDHERSAWithAES128CBCSHA => 0xC013,
+            CipherSuite::TLSECDHERSAWithAES256CBCSHA => 0xC014,
+            CipherSuite::TLSECDHERSAWithAES128GCMSHA256 => 0xC02F,
+            CipherSuite::TLSECDHERSAWithAES256GCMSHA384 => 0xC030,
+            CipherSuite::TLSECDHAnonWithNullSHA => 0xC015,
+            CipherSuite::TLSECDHAnonWith3DESEDECBCSHA => 0xC017,
+            CipherSuite::TLSECDHAnonWithAES128CBCSHA => 0xC018,
+            CipherSuite::TLSECDHAnonWithAES256CBCSHA => 0xC019,
+            // RFC 7905 ChaCha20-Poly1305 Cipher Suites for TLS
+            CipherSuite::TLSECDHERSAWithChaCha20Poly1305SHA256 => 0xCCA8,
+            CipherSuite::TLSECDHEECDSAWithChaCha20Poly1305SHA256 => 0xCCA9,
+            CipherSuite::TLSDHERSAWithChaCha20Poly1305SHA256 => 0xCCAA,
+            CipherSuite::TLSPSKWithChaCha20Poly1305SHA256 => 0xCCAB,
+            CipherSuite::TLSECDHEPSKWithChaCha20Poly1305SHA256 => 0xCCAC,
+            CipherSuite::TLSDHEPSKWithChaCha20Poly1305SHA256 => 0xCCAD,
+            CipherSuite::TLSRSAPSKWithChaCha20Poly1305SHA256 => 0xCCAE,
+            CipherSuite::TLSAES128GCMSHA256 => 0x1301,
+            CipherSuite::TLSAES256GCMSHA384 => 0x1302,
+            CipherSuite::TLSChaCha20Poly1305SHA256 => 0x1303,
+            CipherSuite::TLSAES128CCMSHA256 => 0x1304,
+            CipherSuite::TLSAES128CCM8SHA256 => 0x1305,
+            CipherSuite::GREASE(b0, b1) | CipherSuite::UNKNOWN(b0, b1) => u16::from_be_bytes([*b0, *b1]),
+        }
+    }
 }
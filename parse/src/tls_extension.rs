@@ -1,110 +1,538 @@
+use crate::cursor::Cursor;
+use crate::writer;
+
 #[derive(Debug,PartialEq)]
 pub enum Extension {
-    ServerName,
-    MaxFragmentLength,
-    StatusRequest,
+    ServerName(ServerNameList),
+    MaxFragmentLength(MaxFragmentLength),
+    // Empty when acknowledged by a server.
+    StatusRequest(Option<CertificateStatusRequest>),
     SupportedGroups(Vec<NamedGroup>),
-    ECPointFormats,
+    ECPointFormats(Vec<ECPointFormat>),
     SignatureAlgorithms(Vec<SignatureScheme>),
-    UseSRTP,
-    Heartbeat,
+    // Extension data that is not decoded is kept encoded.
+    UseSRTP(Vec<u8>),
+    Heartbeat(Vec<u8>),
     ApplicationLayerProtocolNegotiation(ALPN),
-    SignedCertificateTimestamp,
-    ClientCertificateType,
-    ServerCertificateType,
-    Padding,
+    SignedCertificateTimestamp(Vec<u8>),
+    ClientCertificateType(Vec<u8>),
+    ServerCertificateType(Vec<u8>),
+    // Number of padding bytes.
+    Padding(usize),
     EncryptThenMAC,
     ExtendedMasterSecret,
-    PreSharedKey,
-    EarlyData,
-    SupportedVersions,
-    Cookie,
-    PSKKeyExchangeModes,
-    CertificateAuthorities,
-    OIDFilters,
+    PreSharedKey(PreSharedKey),
+    EarlyData(Vec<u8>),
+    SupportedVersions(SupportedVersions),
+    Cookie(Vec<u8>),
+    PSKKeyExchangeModes(Vec<PSKKeyExchangeMode>),
+    CertificateAuthorities(Vec<u8>),
+    OIDFilters(Vec<u8>),
     PostHandshakeAuth,
-    SignatureAlgorithmsCert,
-    KeyShare,
+    SignatureAlgorithmsCert(Vec<SignatureScheme>),
+    KeyShare(KeyShare),
+    // RFC 8701 - Reserved values sent to keep servers tolerant of unknown extensions.
+    Grease { id: u16, data: Vec<u8> },
+    Unknown { id: u16, data: Vec<u8> },
+    // Extension data that would not be written back as sent once decoded, such as malformed
+    // data, names that are not UTF-8 or padding that is not zero.
+    Undecoded { id: u16, data: Vec<u8> },
+}
+
+// RFC 8701 2 - GREASE values are 0x?A?A with both bytes equal, in every field that uses them.
+pub fn is_grease(value: u16) -> bool {
+    let [b0, b1] = value.to_be_bytes();
+    return b0 == b1 && b0 & 0x0F == 0x0A;
+}
+
+// RFC 8446 4.2 - The message carrying the extensions. Some extensions have a different layout in
+// each.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ExtensionContext {
+    ClientHello,
+    ServerHello,
+    HelloRetryRequest,
+}
+
+fn u16_list(values: impl Iterator<Item = u16>) -> Vec<u8> {
+    values.flat_map(|value| value.to_be_bytes()).collect()
 }
 
 impl Extension {
-    pub fn new<'a>(data: &mut impl Iterator<Item = &'a u8>) -> Result<Self, u16> {
-        if let (Some(b0), Some(b1)) = (data.next(), data.next()) {
-            let extension_type = u16::from_be_bytes([*b0, *b1]);
-            if let (Some(b0), Some(b1)) = (data.next(), data.next()) {
-                let extension_data_length: u16 = u16::from_be_bytes([*b0, *b1]);
-                let mut extension_data = Vec::<u8>::new();
-                for _ in 0..extension_data_length {
-                    if let Some(b) = data.next() {
-                        extension_data.push(u8::clone(b));
-                    } else {
-                        return Err(0);
+    pub fn new(extension_type: u16, extension_data: &[u8], context: ExtensionContext) -> Self {
+        let extension = Self::decode(extension_type, extension_data, context);
+        if extension.data().as_deref() != Some(extension_data) {
+            return Extension::Undecoded { id: extension_type, data: extension_data.to_vec() };
+        }
+        return extension;
+    }
+
+    fn decode(extension_type: u16, extension_data: &[u8], context: ExtensionContext) -> Self {
+        match extension_type {
+            // RFC 6066 TLS Extensions: Extension Definitions
+            0 => Extension::ServerName(ServerNameList::new(extension_data)),
+            1 => Extension::MaxFragmentLength(MaxFragmentLength::new(extension_data.first().copied().unwrap_or(0))),
+            5 => Extension::StatusRequest(CertificateStatusRequest::new(extension_data)),
+            10 => Extension::SupportedGroups(Self::parse_named_groups(extension_data)),
+            // RFC 4492 ECC Cipher Suites
+            11 => Extension::ECPointFormats(Self::parse_ec_point_formats(extension_data)),
+            13 => Extension::SignatureAlgorithms(Self::parse_signature_schemes(extension_data)),
+            14 => Extension::UseSRTP(extension_data.to_vec()),
+            15 => Extension::Heartbeat(extension_data.to_vec()),
+            // RFC TLS 7301 Application-Layer Protocol Negotiation
+            16 => Extension::ApplicationLayerProtocolNegotiation(ALPN::new(extension_data)),
+            18 => Extension::SignedCertificateTimestamp(extension_data.to_vec()),
+            19 => Extension::ClientCertificateType(extension_data.to_vec()),
+            20 => Extension::ServerCertificateType(extension_data.to_vec()),
+            // RFC 7685 A TLS ClientHello Padding Extension
+            21 => Extension::Padding(extension_data.len()),
+            // RFC 7366 Encrypt-then-MAC for TLS
+            22 => Extension::EncryptThenMAC,
+            // RFC 7627 TLS Session Hash and Extended Master Secret Extension
+            23 => Extension::ExtendedMasterSecret,
+            // RFC 8446 TLS 1.3
+            41 => Extension::PreSharedKey(PreSharedKey::new(extension_data, context)),
+            42 => Extension::EarlyData(extension_data.to_vec()),
+            43 => Extension::SupportedVersions(SupportedVersions::new(extension_data, context)),
+            44 => Extension::Cookie(Cursor::new(extension_data).vector(2, "cookie").map(|cookie| cookie.remaining().to_vec()).unwrap_or_default()),
+            45 => Extension::PSKKeyExchangeModes(Self::parse_psk_key_exchange_modes(extension_data)),
+            47 => Extension::CertificateAuthorities(extension_data.to_vec()),
+            48 => Extension::OIDFilters(extension_data.to_vec()),
+            49 => Extension::PostHandshakeAuth,
+            50 => Extension::SignatureAlgorithmsCert(Self::parse_signature_schemes(extension_data)),
+            51 => Extension::KeyShare(KeyShare::new(extension_data, context)),
+            _ if is_grease(extension_type) => Extension::Grease { id: extension_type, data: extension_data.to_vec() },
+            _ => Extension::Unknown { id: extension_type, data: extension_data.to_vec() },
+        }
+    }
+
+    // The extension type identifying the extension on the wire.
+    pub fn id(&self) -> u16 {
+        match self {
+            // RFC 6066 TLS Extensions: Extension Definitions
+            Extension::ServerName(_) => 0,
+            Extension::MaxFragmentLength(_) => 1,
+            Extension::StatusRequest(_) => 5,
+            Extension::SupportedGroups(_) => 10,
+            // RFC 4492 ECC Cipher Suites
+            Extension::ECPointFormats(_) => 11,
+            Extension::SignatureAlgorithms(_) => 13,
+            Extension::UseSRTP(_) => 14,
+            Extension::Heartbeat(_) => 15,
+            // RFC TLS 7301 Application-Layer Protocol Negotiation
+            Extension::ApplicationLayerProtocolNegotiation(_) => 16,
+            Extension::SignedCertificateTimestamp(_) => 18,
+            Extension::ClientCertificateType(_) => 19,
+            Extension::ServerCertificateType(_) => 20,
+            // RFC 7685 A TLS ClientHello Padding Extension
+            Extension::Padding(_) => 21,
+            // RFC 7366 Encrypt-then-MAC for TLS
+            Extension::EncryptThenMAC => 22,
+            // RFC 7627 TLS Session Hash and Extended Master Secret Extension
+            Extension::ExtendedMasterSecret => 23,
+            // RFC 8446 TLS 1.3
+            Extension::PreSharedKey(_) => 41,
+            Extension::EarlyData(_) => 42,
+            Extension::SupportedVersions(_) => 43,
+            Extension::Cookie(_) => 44,
+            Extension::PSKKeyExchangeModes(_) => 45,
+            Extension::CertificateAuthorities(_) => 47,
+            Extension::OIDFilters(_) => 48,
+            Extension::PostHandshakeAuth => 49,
+            Extension::SignatureAlgorithmsCert(_) => 50,
+            Extension::KeyShare(_) => 51,
+            Extension::Grease { id, .. } | Extension::Unknown { id, .. } | Extension::Undecoded { id, .. } => *id,
+        }
+    }
+
+    // The extension type followed by the extension data, as sent. None if a field is too long for
+    // its length prefix.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut out = self.id().to_be_bytes().to_vec();
+        writer::vector(&mut out, 2, &self.data()?)?;
+        return Some(out);
+    }
+
+    // Encode the payload as extension data. Extensions without a payload have none.
+    fn data(&self) -> Option<Vec<u8>> {
+        let mut data = Vec::new();
+        match self {
+            Extension::ServerName(list) => {
+                // Servers acknowledge the extension with empty extension data.
+                if !list.host_names.is_empty() {
+                    let mut names = Vec::new();
+                    for host_name in list.host_names.iter() {
+                        names.push(0);
+                        writer::vector(&mut names, 2, host_name.as_bytes())?;
                     }
+                    writer::vector(&mut data, 2, &names)?;
                 }
-                return match extension_type {
-                    0 => Ok(Extension::ServerName),
-                    1 => Ok(Extension::MaxFragmentLength),
-                    5 => Ok(Extension::StatusRequest),
-                    10 => Ok(Extension::SupportedGroups(Self::parse_named_groups(extension_data))),
-                    // RFC 4492 ECC Cipher Suites
-                    11 => Ok(Extension::ECPointFormats),
-                    13 => Ok(Extension::SignatureAlgorithms(Self::parse_signature_schemes(extension_data))),
-                    14 => Ok(Extension::UseSRTP),
-                    15 => Ok(Extension::Heartbeat),
-                    // RFC TLS 7301 Application-Layer Protocol Negotiation
-                    16 => Ok(Extension::ApplicationLayerProtocolNegotiation(ALPN::new(extension_data))),
-                    18 => Ok(Extension::SignedCertificateTimestamp),
-                    19 => Ok(Extension::ClientCertificateType),
-                    20 => Ok(Extension::ServerCertificateType),
-                    21 => Ok(Extension::Padding),
-                    // RFC 7366 Encrypt-then-MAC for TLS
-                    22 => Ok(Extension::EncryptThenMAC),
-                    // RFC 7627 TLS Session Hash and Extended Master Secret Extension
-                    23 => Ok(Extension::ExtendedMasterSecret),
-                    41 => Ok(Extension::PreSharedKey),
-                    42 => Ok(Extension::EarlyData),
-                    43 => Ok(Extension::SupportedVersions),
-                    44 => Ok(Extension::Cookie),
-                    45 => Ok(Extension::PSKKeyExchangeModes),
-                    47 => Ok(Extension::CertificateAuthorities),
-                    48 => Ok(Extension::OIDFilters),
-                    49 => Ok(Extension::PostHandshakeAuth),
-                    50 => Ok(Extension::SignatureAlgorithmsCert),
-                    51 => Ok(Extension::KeyShare),
-                    _ => Err(extension_type),
-                };
-            }
-        }
-        return Err(0);
+            },
+            Extension::MaxFragmentLength(length) => data.push(length.id()),
+            Extension::StatusRequest(Some(request)) => {
+                data.push(request.status_type);
+                let mut responder_ids = Vec::new();
+                for responder_id in request.responder_id_list.iter() {
+                    writer::vector(&mut responder_ids, 2, responder_id)?;
+                }
+                writer::vector(&mut data, 2, &responder_ids)?;
+                writer::vector(&mut data, 2, &request.request_extensions)?;
+            },
+            Extension::SupportedGroups(groups) => writer::vector(&mut data, 2, &u16_list(groups.iter().map(|group| group.id())))?,
+            Extension::ECPointFormats(formats) => writer::vector(&mut data, 1, &formats.iter().map(|format| format.id()).collect::<Vec<u8>>())?,
+            Extension::SignatureAlgorithms(schemes) | Extension::SignatureAlgorithmsCert(schemes) => {
+                writer::vector(&mut data, 2, &u16_list(schemes.iter().map(|scheme| scheme.id())))?;
+            },
+            Extension::ApplicationLayerProtocolNegotiation(alpn) => {
+                let mut names = Vec::new();
+                for name in alpn.protocol_name_list.iter() {
+                    writer::vector(&mut names, 1, name.as_bytes())?;
+                }
+                writer::vector(&mut data, 2, &names)?;
+            },
+            Extension::Padding(length) => data.resize(*length, 0),
+            Extension::PreSharedKey(PreSharedKey::Offered { identities, binders }) => {
+                let mut offered = Vec::new();
+                for identity in identities.iter() {
+                    writer::vector(&mut offered, 2, &identity.identity)?;
+                    offered.extend_from_slice(&identity.obfuscated_ticket_age.to_be_bytes());
+                }
+                writer::vector(&mut data, 2, &offered)?;
+                let mut offered = Vec::new();
+                for binder in binders.iter() {
+                    writer::vector(&mut offered, 1, binder)?;
+                }
+                writer::vector(&mut data, 2, &offered)?;
+            },
+            Extension::PreSharedKey(PreSharedKey::Selected(identity)) => data.extend_from_slice(&identity.to_be_bytes()),
+            Extension::SupportedVersions(SupportedVersions::Offered(versions)) => writer::vector(&mut data, 1, &u16_list(versions.iter().map(|version| version.id())))?,
+            Extension::SupportedVersions(SupportedVersions::Selected(version)) => data.extend_from_slice(&version.id().to_be_bytes()),
+            Extension::Cookie(cookie) => writer::vector(&mut data, 2, cookie)?,
+            Extension::PSKKeyExchangeModes(modes) => writer::vector(&mut data, 1, &modes.iter().map(|mode| mode.id()).collect::<Vec<u8>>())?,
+            Extension::KeyShare(KeyShare::ClientShares(entries)) => {
+                let mut shares = Vec::new();
+                for entry in entries.iter() {
+                    entry.write(&mut shares)?;
+                }
+                writer::vector(&mut data, 2, &shares)?;
+            },
+            Extension::KeyShare(KeyShare::ServerShare(entry)) => entry.write(&mut data)?,
+            Extension::KeyShare(KeyShare::SelectedGroup(group)) => data.extend_from_slice(&group.id().to_be_bytes()),
+            Extension::UseSRTP(raw) | Extension::Heartbeat(raw) | Extension::SignedCertificateTimestamp(raw)
+                | Extension::ClientCertificateType(raw) | Extension::ServerCertificateType(raw) | Extension::EarlyData(raw)
+                | Extension::CertificateAuthorities(raw) | Extension::OIDFilters(raw)
+                | Extension::Grease { data: raw, .. } | Extension::Unknown { data: raw, .. }
+                | Extension::Undecoded { data: raw, .. } => data.extend_from_slice(raw),
+            Extension::StatusRequest(None) | Extension::EncryptThenMAC | Extension::ExtendedMasterSecret | Extension::PostHandshakeAuth => {},
+        };
+        return Some(data);
     }
 
-    fn parse_signature_schemes(data: Vec<u8>) -> Vec<SignatureScheme> {
-        let mut i = 0;
+    // RFC 8446 4.2.3 - A list of schemes preceded by its length in two bytes.
+    fn parse_signature_schemes(data: &[u8]) -> Vec<SignatureScheme> {
+        let mut schemes = Cursor::new(data).vector(2, "supported_signature_algorithms").unwrap_or(Cursor::new(&[]));
         let mut supported_signature_algorithms: Vec<SignatureScheme> = Vec::new();
-        let data_length: u16 = u16::from_be_bytes([data[0], data[1]]);
-        i = i + 2;
-        let data_length = usize::from(data_length);
-        while i < data_length {
-            let id: u16 = u16::from_be_bytes([data[i], data[i + 1]]);
-            i = i + 2;
+        while let Ok(id) = schemes.u16("signature_scheme") {
             supported_signature_algorithms.push(SignatureScheme::new(id));
         }
         return supported_signature_algorithms;
     }
 
-    fn parse_named_groups(data: Vec<u8>) -> Vec<NamedGroup> {
-        let mut i = 0;
+    // RFC 8446 4.2.7 - A list of groups preceded by its length in two bytes.
+    fn parse_named_groups(data: &[u8]) -> Vec<NamedGroup> {
+        let mut groups = Cursor::new(data).vector(2, "named_group_list").unwrap_or(Cursor::new(&[]));
         let mut named_group_list: Vec<NamedGroup> = Vec::new();
-        let data_length: u16 = u16::from_be_bytes([data[0], data[1]]);
-        i = i + 2;
-        let data_length = usize::from(data_length);
-        while i < data_length {
-            let id: u16 = u16::from_be_bytes([data[i], data[i + 1]]);
-            i = i + 2;
+        while let Ok(id) = groups.u16("named_group") {
             named_group_list.push(NamedGroup::new(id));
         }
         return named_group_list;
     }
+
+    // RFC 8422 5.1.2 - A list of formats preceded by its length in one byte.
+    fn parse_ec_point_formats(data: &[u8]) -> Vec<ECPointFormat> {
+        let mut formats = Cursor::new(data).vector(1, "ec_point_format_list").unwrap_or(Cursor::new(&[]));
+        let mut ec_point_format_list: Vec<ECPointFormat> = Vec::new();
+        while let Ok(format) = formats.u8("ec_point_format") {
+            ec_point_format_list.push(ECPointFormat::new(format));
+        }
+        return ec_point_format_list;
+    }
+
+    // RFC 8446 4.2.9 - A list of modes preceded by its length in one byte.
+    fn parse_psk_key_exchange_modes(data: &[u8]) -> Vec<PSKKeyExchangeMode> {
+        let mut modes = Cursor::new(data).vector(1, "ke_modes").unwrap_or(Cursor::new(&[]));
+        let mut ke_modes: Vec<PSKKeyExchangeMode> = Vec::new();
+        while let Ok(mode) = modes.u8("ke_mode") {
+            ke_modes.push(PSKKeyExchangeMode::new(mode));
+        }
+        return ke_modes;
+    }
+}
+
+// RFC 6066 4 - The maximum fragment length requested by the client.
+#[derive(Debug,PartialEq)]
+pub enum MaxFragmentLength {
+    Bytes512,
+    Bytes1024,
+    Bytes2048,
+    Bytes4096,
+    Missing(u8),
+}
+
+impl MaxFragmentLength {
+    pub fn new(n: u8) -> Self {
+        match n {
+            1 => MaxFragmentLength::Bytes512,
+            2 => MaxFragmentLength::Bytes1024,
+            3 => MaxFragmentLength::Bytes2048,
+            4 => MaxFragmentLength::Bytes4096,
+            _ => MaxFragmentLength::Missing(n),
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            MaxFragmentLength::Bytes512 => 1,
+            MaxFragmentLength::Bytes1024 => 2,
+            MaxFragmentLength::Bytes2048 => 3,
+            MaxFragmentLength::Bytes4096 => 4,
+            MaxFragmentLength::Missing(n) => *n,
+        }
+    }
+}
+
+// RFC 6066 8 - The OCSP responders trusted by the client and the extensions to include in the
+// OCSP request, both kept encoded.
+#[derive(Debug,PartialEq)]
+pub struct CertificateStatusRequest {
+    pub status_type: u8,
+    pub responder_id_list: Vec<Vec<u8>>,
+    pub request_extensions: Vec<u8>,
+}
+
+impl CertificateStatusRequest {
+    fn new(data: &[u8]) -> Option<Self> {
+        let mut data = Cursor::new(data);
+        let status_type = data.u8("status_type").ok()?;
+        let mut responder_id_list: Vec<Vec<u8>> = Vec::new();
+        if let Ok(mut responder_ids) = data.vector(2, "responder_id_list") {
+            while let Ok(responder_id) = responder_ids.vector(2, "responder_id") {
+                responder_id_list.push(responder_id.remaining().to_vec());
+            }
+        }
+        let request_extensions = data.vector(2, "request_extensions").map(|extensions| extensions.remaining().to_vec()).unwrap_or_default();
+        return Some(CertificateStatusRequest {
+            status_type: status_type,
+            responder_id_list: responder_id_list,
+            request_extensions: request_extensions,
+        });
+    }
+}
+
+// RFC 8422 5.1.2
+#[derive(Debug,PartialEq)]
+pub enum ECPointFormat {
+    Uncompressed,
+    ANSIX962CompressedPrime,
+    ANSIX962CompressedChar2,
+    Missing(u8),
+}
+
+impl ECPointFormat {
+    pub fn new(n: u8) -> Self {
+        match n {
+            0 => ECPointFormat::Uncompressed,
+            1 => ECPointFormat::ANSIX962CompressedPrime,
+            2 => ECPointFormat::ANSIX962CompressedChar2,
+            _ => ECPointFormat::Missing(n),
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            ECPointFormat::Uncompressed => 0,
+            ECPointFormat::ANSIX962CompressedPrime => 1,
+            ECPointFormat::ANSIX962CompressedChar2 => 2,
+            ECPointFormat::Missing(n) => *n,
+        }
+    }
+}
+
+// RFC 8446 4.2.11 - Clients offer identities and a binder for each. Servers select one of them
+// by its index.
+#[derive(Debug,PartialEq)]
+pub enum PreSharedKey {
+    Offered { identities: Vec<PSKIdentity>, binders: Vec<Vec<u8>> },
+    Selected(u16),
+}
+
+#[derive(Debug,PartialEq)]
+pub struct PSKIdentity {
+    pub identity: Vec<u8>,
+    pub obfuscated_ticket_age: u32,
+}
+
+impl PreSharedKey {
+    fn new(data: &[u8], context: ExtensionContext) -> Self {
+        let mut data = Cursor::new(data);
+        if context != ExtensionContext::ClientHello {
+            return PreSharedKey::Selected(data.u16("selected_identity").unwrap_or(0));
+        }
+        let mut identities: Vec<PSKIdentity> = Vec::new();
+        if let Ok(mut offered) = data.vector(2, "identities") {
+            while let (Ok(identity), Ok(obfuscated_ticket_age)) = (offered.vector(2, "identity"), offered.u32("obfuscated_ticket_age")) {
+                identities.push(PSKIdentity {
+                    identity: identity.remaining().to_vec(),
+                    obfuscated_ticket_age: obfuscated_ticket_age,
+                });
+            }
+        }
+        let mut binders: Vec<Vec<u8>> = Vec::new();
+        if let Ok(mut offered) = data.vector(2, "binders") {
+            while let Ok(binder) = offered.vector(1, "binder") {
+                binders.push(binder.remaining().to_vec());
+            }
+        }
+        return PreSharedKey::Offered { identities: identities, binders: binders };
+    }
+}
+
+// RFC 8446 4.2.1 - Clients list the versions they support, preceded by the length of the list in
+// one byte. Servers reply with the selected version alone.
+#[derive(Debug,PartialEq)]
+pub enum SupportedVersions {
+    Offered(Vec<ProtocolVersion>),
+    Selected(ProtocolVersion),
+}
+
+impl SupportedVersions {
+    fn new(data: &[u8], context: ExtensionContext) -> Self {
+        let mut data = Cursor::new(data);
+        if context != ExtensionContext::ClientHello {
+            return SupportedVersions::Selected(ProtocolVersion::new(data.u16("selected_version").unwrap_or(0)));
+        }
+        let mut versions = data.vector(1, "versions").unwrap_or(Cursor::new(&[]));
+        let mut offered: Vec<ProtocolVersion> = Vec::new();
+        while let Ok(version) = versions.u16("version") {
+            offered.push(ProtocolVersion::new(version));
+        }
+        return SupportedVersions::Offered(offered);
+    }
+}
+
+#[derive(Debug,PartialEq)]
+pub enum ProtocolVersion {
+    SSL30,
+    TLS10,
+    TLS11,
+    TLS12,
+    TLS13,
+    Grease(u16),
+    Missing(u16),
+}
+
+impl ProtocolVersion {
+    pub fn new(n: u16) -> Self {
+        match n {
+            0x0300 => ProtocolVersion::SSL30,
+            0x0301 => ProtocolVersion::TLS10,
+            0x0302 => ProtocolVersion::TLS11,
+            0x0303 => ProtocolVersion::TLS12,
+            0x0304 => ProtocolVersion::TLS13,
+            _ if is_grease(n) => ProtocolVersion::Grease(n),
+            _ => ProtocolVersion::Missing(n),
+        }
+    }
+
+    pub fn id(&self) -> u16 {
+        match self {
+            ProtocolVersion::SSL30 => 0x0300,
+            ProtocolVersion::TLS10 => 0x0301,
+            ProtocolVersion::TLS11 => 0x0302,
+            ProtocolVersion::TLS12 => 0x0303,
+            ProtocolVersion::TLS13 => 0x0304,
+            ProtocolVersion::Grease(n) | ProtocolVersion::Missing(n) => *n,
+        }
+    }
+}
+
+// RFC 8446 4.2.9
+#[derive(Debug,PartialEq)]
+pub enum PSKKeyExchangeMode {
+    PSKKE,
+    PSKDHEKE,
+    Missing(u8),
+}
+
+impl PSKKeyExchangeMode {
+    pub fn new(n: u8) -> Self {
+        match n {
+            0 => PSKKeyExchangeMode::PSKKE,
+            1 => PSKKeyExchangeMode::PSKDHEKE,
+            _ => PSKKeyExchangeMode::Missing(n),
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            PSKKeyExchangeMode::PSKKE => 0,
+            PSKKeyExchangeMode::PSKDHEKE => 1,
+            PSKKeyExchangeMode::Missing(n) => *n,
+        }
+    }
+}
+
+// RFC 8446 4.2.8 - Clients send a list of shares preceded by its length, servers a single share
+// in a ServerHello and only the group they selected in a HelloRetryRequest.
+#[derive(Debug,PartialEq)]
+pub enum KeyShare {
+    ClientShares(Vec<KeyShareEntry>),
+    ServerShare(KeyShareEntry),
+    SelectedGroup(NamedGroup),
+}
+
+#[derive(Debug,PartialEq)]
+pub struct KeyShareEntry {
+    pub group: NamedGroup,
+    pub key_exchange: Vec<u8>,
+}
+
+impl KeyShare {
+    fn new(data: &[u8], context: ExtensionContext) -> Self {
+        let mut data = Cursor::new(data);
+        match context {
+            // An empty list asks the server for a HelloRetryRequest selecting the group.
+            ExtensionContext::ClientHello => {
+                let mut shares = data.vector(2, "client_shares").unwrap_or(Cursor::new(&[]));
+                let mut client_shares: Vec<KeyShareEntry> = Vec::new();
+                while let Some(entry) = KeyShareEntry::read(&mut shares) {
+                    client_shares.push(entry);
+                }
+                KeyShare::ClientShares(client_shares)
+            },
+            ExtensionContext::ServerHello => KeyShare::ServerShare(KeyShareEntry::read(&mut data).unwrap_or(KeyShareEntry {
+                group: NamedGroup::new(0),
+                key_exchange: Vec::new(),
+            })),
+            ExtensionContext::HelloRetryRequest => KeyShare::SelectedGroup(NamedGroup::new(data.u16("selected_group").unwrap_or(0))),
+        }
+    }
+}
+
+impl KeyShareEntry {
+    fn read(data: &mut Cursor) -> Option<Self> {
+        let group = data.u16("group").ok()?;
+        let key_exchange = data.vector(2, "key_exchange").ok()?;
+        return Some(KeyShareEntry {
+            group: NamedGroup::new(group),
+            key_exchange: key_exchange.remaining().to_vec(),
+        });
+    }
+
+    fn write(&self, out: &mut Vec<u8>) -> Option<()> {
+        out.extend_from_slice(&self.group.id().to_be_bytes());
+        return writer::vector(out, 2, &self.key_exchange);
+    }
 }
 
 #[derive(Debug,PartialEq)]
@@ -113,20 +541,12 @@ pub struct ALPN {
 }
 
 impl ALPN {
-    fn new(data: Vec<u8>) -> Self {
-        let mut i = 0;
+    // RFC 7301 3.1 - A list of names, each preceded by its length in one byte.
+    fn new(data: &[u8]) -> Self {
+        let mut names = Cursor::new(data).vector(2, "protocol_name_list").unwrap_or(Cursor::new(&[]));
         let mut protocol_name_list: Vec<String> = Vec::new();
-        let data_length: u16 = u16::from_be_bytes([data[0], data[1]]);
-        i = i + 2;
-        let data_length = usize::from(data_length);
-        while i < data_length {
-            let length = usize::from(data[i]);
-            i = i + 1;
-            match std::str::from_utf8(&data[i..i+length]) {
-                Ok(protocol_name) => protocol_name_list.push(protocol_name.to_string()),
-                Err(e) => println!("{e:?}"),
-            }
-            i = i + length;
+        while let Ok(name) = names.vector(1, "protocol_name") {
+            protocol_name_list.push(String::from_utf8_lossy(name.remaining()).to_string());
         }
         return ALPN {
             protocol_name_list: protocol_name_list,
@@ -134,6 +554,31 @@ impl ALPN {
     }
 }
 
+#[derive(Debug,PartialEq)]
+pub struct ServerNameList {
+    pub host_names: Vec<String>,
+}
+
+impl ServerNameList {
+    // RFC 6066 3 - A list of names, each preceded by its type and its length in two bytes. Servers
+    // acknowledge the extension with empty extension data.
+    fn new(data: &[u8]) -> Self {
+        let mut names = Cursor::new(data).vector(2, "server_name_list").unwrap_or(Cursor::new(&[]));
+        let mut host_names: Vec<String> = Vec::new();
+        while let (Ok(name_type), Ok(name)) = (names.u8("name_type"), names.vector(2, "host_name")) {
+            // host_name is the only name type defined.
+            if name_type == 0 {
+                if let Ok(host_name) = std::str::from_utf8(name.remaining()) {
+                    host_names.push(host_name.to_string());
+                }
+            }
+        }
+        return ServerNameList {
+            host_names: host_names,
+        }
+    }
+}
+
 #[derive(Debug,PartialEq)]
 pub enum SignatureScheme {
     // RFC 5246 TLS 1.2
@@ -167,12 +612,13 @@ pub enum SignatureScheme {
     RSAPSSPSSSHA512,
     RSAPKCS1SHA1,
     ECDSASHA1,
-    PrivateUse,
+    PrivateUse(u16),
+    Grease(u16),
     Missing(u16),
 }
 
 impl SignatureScheme {
-    fn new(n: u16) -> Self {
+    pub fn new(n: u16) -> Self {
         match n {
             // RFC 5246 TLS 1.2
             0x0202 => SignatureScheme::SHA1DSA,
@@ -199,10 +645,49 @@ impl SignatureScheme {
             0x080B => SignatureScheme::RSAPSSPSSSHA512,
             0x0201 => SignatureScheme::RSAPKCS1SHA1,
             0x0203 => SignatureScheme::ECDSASHA1,
-            0xFE00..=0xFFFF => SignatureScheme::PrivateUse,
+            0xFE00..=0xFFFF => SignatureScheme::PrivateUse(n),
+            _ if is_grease(n) => SignatureScheme::Grease(n),
             _ => SignatureScheme::Missing(n),
         }
     }
+
+    pub fn id(&self) -> u16 {
+        match self {
+            // RFC 5246 TLS 1.2
+            SignatureScheme::SHA1DSA => 0x0202,
+            SignatureScheme::SHA224RSA => 0x0301,
+            SignatureScheme::SHA224DSA => 0x0302,
+            SignatureScheme::SHA224ECDSA => 0x0303,
+            SignatureScheme::SHA256DSA => 0x0402,
+            SignatureScheme::SHA384DSA => 0x0502,
+            SignatureScheme::SHA512DSA => 0x0602,
+            // RFC 8446 TLS 1.3
+            SignatureScheme::RSAPKCS1SHA256 => 0x0401,
+            SignatureScheme::RSAPKCS1SHA384 => 0x0501,
+            SignatureScheme::RSAPKCS1SHA512 => 0x0601,
+            SignatureScheme::ECDSASECP256R1SHA256 => 0x0403,
+            SignatureScheme::ECDSASECP384R1SHA384 => 0x0503,
+            SignatureScheme::ECDSASECP521R1SHA512 => 0x0603,
+            SignatureScheme::RSAPSSRSAESHA256 => 0x0804,
+            SignatureScheme::RSAPSSRSAESHA384 => 0x0805,
+            SignatureScheme::RSAPSSRSAESHA512 => 0x0806,
+            SignatureScheme::ED25519 => 0x0807,
+            SignatureScheme::ED448 => 0x0808,
+            SignatureScheme::RSAPSSPSSSHA256 => 0x0809,
+            SignatureScheme::RSAPSSPSSSHA384 => 0x080A,
+            SignatureScheme::RSAPSSPSSSHA512 => 0x080B,
+            SignatureScheme::RSAPKCS1SHA1 => 0x0201,
+            SignatureScheme::ECDSASHA1 => 0x0203,
+            // TLS 1.2 names of the TLS 1.3 schemes with the same values.
+            SignatureScheme::SHA256RSA => 0x0401,
+            SignatureScheme::SHA256ECDSA => 0x0403,
+            SignatureScheme::SHA384RSA => 0x0501,
+            SignatureScheme::SHA384ECDSA => 0x0503,
+            SignatureScheme::SHA512RSA => 0x0601,
+            SignatureScheme::SHA512ECDSA => 0x0603,
+            SignatureScheme::PrivateUse(n) | SignatureScheme::Grease(n) | SignatureScheme::Missing(n) => *n,
+        }
+    }
 }
 
 #[derive(Debug,PartialEq)]
@@ -218,13 +703,14 @@ pub enum NamedGroup {
     FFDHE4096,
     FFDHE6144,
     FFDHE8192,
-    FFDHEPrivateUse,
-    ECDHEPrivateUse,
+    FFDHEPrivateUse(u16),
+    ECDHEPrivateUse(u16),
+    Grease(u16),
     Missing(u16),
 }
 
 impl NamedGroup {
-    fn new(n: u16) -> Self {
+    pub fn new(n: u16) -> Self {
         match n {
             // RFC 8446 TLS 1.3
             0x0017 => NamedGroup::SECP256R1,
@@ -237,9 +723,27 @@ impl NamedGroup {
             0x0102 => NamedGroup::FFDHE4096,
             0x0103 => NamedGroup::FFDHE6144,
             0x0104 => NamedGroup::FFDHE8192,
-            0x01FC..=0x01FF => NamedGroup::FFDHEPrivateUse,
-            0xFE00..=0xFEFF => NamedGroup::ECDHEPrivateUse,
+            0x01FC..=0x01FF => NamedGroup::FFDHEPrivateUse(n),
+            0xFE00..=0xFEFF => NamedGroup::ECDHEPrivateUse(n),
+            _ if is_grease(n) => NamedGroup::Grease(n),
             _ => NamedGroup::Missing(n),
         }
     }
+
+    pub fn id(&self) -> u16 {
+        match self {
+            // RFC 8446 TLS 1.3
+            NamedGroup::SECP256R1 => 0x0017,
+            NamedGroup::SECP384R1 => 0x0018,
+            NamedGroup::SECP521R1 => 0x0019,
+            NamedGroup::X25519 => 0x001D,
+            NamedGroup::X448 => 0x001E,
+            NamedGroup::FFDHE2048 => 0x0100,
+            NamedGroup::FFDHE3072 => 0x0101,
+            NamedGroup::FFDHE4096 => 0x0102,
+            NamedGroup::FFDHE6144 => 0x0103,
+            NamedGroup::FFDHE8192 => 0x0104,
+            NamedGroup::FFDHEPrivateUse(n) | NamedGroup::ECDHEPrivateUse(n) | NamedGroup::Grease(n) | NamedGroup::Missing(n) => *n,
+        }
+    }
 }
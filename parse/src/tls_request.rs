@@ -1,5 +1,14 @@
+use std::fmt;
+
+use crate::cursor::Cursor;
+use crate::der;
+use crate::error::{ParseError, Reason};
+use crate::fingerprint::Fingerprint;
+use crate::tls_alert::Alert;
 use crate::tls_cipher_suite::CipherSuite;
-use crate::tls_extension::Extension;
+use crate::tls_extension::{Extension, ExtensionContext, KeyShare, KeyShareEntry, NamedGroup, ServerNameList, SignatureScheme, ALPN};
+use crate::writer;
+use crate::x509;
 
 // RFC 8446 - TLS 1.3
 #[derive(Debug,PartialEq)]
@@ -8,29 +17,243 @@ pub struct Record {
     pub legacy_record_version: u16,
     pub length: u16,
     pub fragment: Option<Handshake>,
+    pub alert: Option<Alert>,
 }
 
 impl Record {
-    pub fn new(data: &[u8]) -> Option<Self> {
-        let mut data = data.iter();
-
-        let r#type = RecordContentType::new(data.next()?)?;
-        let version: u16 = u16::from_be_bytes([*data.next()?, *data.next()?]);
-        let length: u16 = u16::from_be_bytes([*data.next()?, *data.next()?]);
+    // Parse the first record in the data and the first handshake message or alert in its
+    // fragment.
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        let mut data = Cursor::new(data);
+        let (r#type, version, length, mut fragment) = read_header(&mut data)?;
 
-        let handshake = Handshake::new(data);
+        // Only handshake messages and alerts are parsed. Without the records before it, an alert
+        // record is taken to be encrypted unless it holds only alerts in the clear.
+        let mut handshake = None;
+        let mut alert = None;
+        match r#type {
+            RecordContentType::Handshake => handshake = Some(Handshake::read(&mut fragment)?),
+            RecordContentType::Alert if Alert::is_plaintext(fragment.remaining()) => alert = Some(Alert::read(&mut fragment)?),
+            _ => {},
+        };
 
-        return Some(Record {
+        return Ok(Record {
             r#type: r#type,
             legacy_record_version: version,
             length: length,
             fragment: handshake,
+            alert: alert,
         })
     }
 
     pub fn properties(&self) -> Vec<(String, String)> {
         let mut properties: Vec<(String, String)> = Vec::<(String, String)>::new();
         properties.push((String::from("record"), format!("length={}", self.length)));
+        if let Some(ref handshake) = self.fragment {
+            properties.extend(handshake.properties());
+        }
+        match self.alert {
+            Some(ref alert) => properties.push((String::from("alert"), alert.to_string())),
+            None if self.r#type == RecordContentType::Alert => properties.push((String::from("encrypted alert"), format!("{} bytes", self.length))),
+            None => {},
+        };
+        return properties;
+    }
+
+    // The record as sent, with lengths computed from its contents. Only records of parsed
+    // handshake messages and alerts can be written, and only if every field fits its length
+    // prefix.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let fragment = match (&self.fragment, &self.alert) {
+            (Some(handshake), _) => handshake.to_bytes()?,
+            (None, Some(alert)) => alert.to_bytes(),
+            (None, None) => return None,
+        };
+        let mut out = vec![self.r#type.id()];
+        out.extend_from_slice(&self.legacy_record_version.to_be_bytes());
+        writer::vector(&mut out, 2, &fragment)?;
+        return Some(out);
+    }
+}
+
+// RFC 8446 5.1 - The longest fragment a record may carry, 2^14 bytes.
+pub const MAX_FRAGMENT_LENGTH: usize = 0x4000;
+
+// RFC 8446 5.1 - Read the record header, returning the fragment following it.
+fn read_header<'a>(data: &mut Cursor<'a>) -> Result<(RecordContentType, u16, u16, Cursor<'a>), ParseError> {
+    let type_offset = data.offset();
+    let content_type = data.u8("type")?;
+    let r#type = match RecordContentType::new(&content_type) {
+        Some(r#type) => r#type,
+        None => return Err(ParseError::new(type_offset, "type", Reason::UnknownType(u16::from(content_type)))),
+    };
+    let version: u16 = data.u16("legacy_record_version")?;
+    let length: u16 = data.u16("length")?;
+    let fragment = data.take(usize::from(length), "fragment")?;
+    return Ok((r#type, version, length, fragment));
+}
+
+// Reads the records of a capture in order. A handshake message may be fragmented across
+// consecutive handshake records, or share a record with others, so each record comes with the
+// messages it completes.
+pub struct RecordReader<'a> {
+    data: Cursor<'a>,
+    // Bytes of a handshake message waiting for the rest of the message.
+    handshake: Vec<u8>,
+    // Where each fragment making up the handshake bytes starts, in those bytes and in the capture.
+    fragments: Vec<(usize, usize)>,
+    // Handshake records following a change of cipher spec are encrypted.
+    encrypted: bool,
+    failed: bool,
+}
+
+// A record and the messages completed by its fragment.
+#[derive(Debug,PartialEq)]
+pub struct RecordMessages {
+    // Offset of the record in the capture.
+    pub offset: usize,
+    pub r#type: RecordContentType,
+    pub legacy_record_version: u16,
+    pub length: u16,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug,PartialEq)]
+pub enum Message {
+    Handshake(Handshake),
+    ChangeCipherSpec,
+    Alert(Alert),
+    // A handshake or alert record protected by a cipher spec changed to earlier.
+    EncryptedHandshake(Vec<u8>),
+    EncryptedAlert(Vec<u8>),
+    ApplicationData(Vec<u8>),
+}
+
+impl<'a> RecordReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        RecordReader {
+            data: Cursor::new(data),
+            handshake: Vec::new(),
+            fragments: Vec::new(),
+            encrypted: false,
+            failed: false,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<RecordMessages, ParseError> {
+        let offset = self.data.offset();
+        let (r#type, version, length, mut fragment) = read_header(&mut self.data)?;
+        let messages = match r#type {
+            RecordContentType::Handshake if self.encrypted => vec![Message::EncryptedHandshake(fragment.remaining().to_vec())],
+            RecordContentType::Handshake => {
+                self.fragments.push((self.handshake.len(), fragment.offset()));
+                self.handshake.extend_from_slice(fragment.remaining());
+                self.complete_handshakes()?
+            },
+            RecordContentType::ChangeCipherSpec => {
+                self.encrypted = true;
+                vec![Message::ChangeCipherSpec]
+            },
+            RecordContentType::Alert if self.encrypted => vec![Message::EncryptedAlert(fragment.remaining().to_vec())],
+            RecordContentType::Alert => {
+                // Each alert is two bytes, and a record may hold several.
+                let mut alerts = Vec::new();
+                while !fragment.is_empty() {
+                    alerts.push(Message::Alert(Alert::read(&mut fragment)?));
+                }
+                alerts
+            },
+            RecordContentType::ApplicationData => vec![Message::ApplicationData(fragment.remaining().to_vec())],
+            RecordContentType::Invalid => Vec::new(),
+        };
+
+        return Ok(RecordMessages {
+            offset: offset,
+            r#type: r#type,
+            legacy_record_version: version,
+            length: length,
+            messages: messages,
+        });
+    }
+
+    // Parse and remove the complete handshake messages at the start of the handshake bytes.
+    fn complete_handshakes(&mut self) -> Result<Vec<Message>, ParseError> {
+        let mut messages = Vec::new();
+        let mut consumed = 0;
+        // RFC 8446 4 - Handshake messages start with their type and a 24-bit length.
+        while let Some(header) = self.handshake.get(consumed..consumed + 4) {
+            let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            let message = match self.handshake.get(consumed..consumed + 4 + length) {
+                Some(message) => message,
+                None => break,
+            };
+            match Handshake::new(message) {
+                Ok(handshake) => messages.push(Message::Handshake(handshake)),
+                Err(mut error) => {
+                    error.offset = self.capture_offset(consumed + error.offset);
+                    return Err(error);
+                },
+            };
+            consumed += 4 + length;
+        }
+
+        let start = self.capture_offset(consumed);
+        self.handshake.drain(..consumed);
+        self.fragments.retain(|(fragment, _)| *fragment > consumed);
+        for fragment in self.fragments.iter_mut() {
+            fragment.0 -= consumed;
+        }
+        if !self.handshake.is_empty() && self.fragments.first().map(|(fragment, _)| *fragment) != Some(0) {
+            self.fragments.insert(0, (0, start));
+        }
+        return Ok(messages);
+    }
+
+    // Map an offset in the handshake bytes to the capture.
+    fn capture_offset(&self, offset: usize) -> usize {
+        match self.fragments.iter().rev().find(|(fragment, _)| *fragment <= offset) {
+            Some((fragment, capture)) => capture + offset - fragment,
+            None => offset,
+        }
+    }
+}
+
+impl Iterator for RecordReader<'_> {
+    type Item = Result<RecordMessages, ParseError>;
+
+    // Reading stops after the first error. A handshake message left incomplete by the last record
+    // is an error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.data.is_empty() {
+            if self.handshake.is_empty() {
+                return None;
+            }
+            self.failed = true;
+            return Some(Err(ParseError::new(self.capture_offset(0), "handshake", Reason::Truncated)));
+        }
+        let record = self.read_record();
+        self.failed = record.is_err();
+        return Some(record);
+    }
+}
+
+impl RecordMessages {
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties: Vec<(String, String)> = Vec::<(String, String)>::new();
+        properties.push((String::from("record"), format!("{:?} offset={} length={}", self.r#type, self.offset, self.length)));
+        for message in self.messages.iter() {
+            match message {
+                Message::Handshake(handshake) => properties.extend(handshake.properties()),
+                Message::ChangeCipherSpec => properties.push((String::from("change_cipher_spec"), String::new())),
+                Message::Alert(alert) => properties.push((String::from("alert"), alert.to_string())),
+                Message::EncryptedHandshake(data) => properties.push((String::from("encrypted handshake"), format!("{} bytes", data.len()))),
+                Message::EncryptedAlert(data) => properties.push((String::from("encrypted alert"), format!("{} bytes", data.len()))),
+                Message::ApplicationData(data) => properties.push((String::from("application data"), format!("{} bytes", data.len()))),
+            };
+        }
         return properties;
     }
 }
@@ -55,6 +278,16 @@ impl RecordContentType {
             _ => None,
         }
     }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            RecordContentType::Invalid => 0,
+            RecordContentType::ChangeCipherSpec => 20,
+            RecordContentType::Alert => 21,
+            RecordContentType::Handshake => 22,
+            RecordContentType::ApplicationData => 23,
+        }
+    }
 }
 
 #[derive(Debug,PartialEq)]
@@ -65,18 +298,88 @@ pub struct Handshake {
 }
 
 impl Handshake {
-    fn new(mut data: std::slice::Iter<u8>) -> Option<Self> {
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        Self::read(&mut Cursor::new(data))
+    }
+
+    fn read(data: &mut Cursor) -> Result<Self, ParseError> {
         // Parse handshake protocol
-        let msg_type = HandshakeType::new(data.next()?)?;
-        let length: u32 = u32::from_be_bytes([0x00, *data.next()?, *data.next()?, *data.next()?]);
-        let msg = HandshakeMessage::new(data);
+        let type_offset = data.offset();
+        let handshake_type = data.u8("msg_type")?;
+        let msg_type = match HandshakeType::new(&handshake_type) {
+            Some(msg_type) => msg_type,
+            None => return Err(ParseError::new(type_offset, "msg_type", Reason::UnknownType(u16::from(handshake_type)))),
+        };
+        let length: u32 = data.u24("length")?;
+        let mut body = data.take(length as usize, "msg")?;
+        // Only hello, certificate and TLS 1.2 key exchange messages are parsed.
+        let msg = match msg_type {
+            HandshakeType::ClientHello => Some(HandshakeMessage::ClientHello(ClientHello::read(&mut body)?)),
+            HandshakeType::ServerHello => {
+                let hello = ServerHello::read(&mut body)?;
+                if hello.random == HELLO_RETRY_REQUEST_RANDOM {
+                    Some(HandshakeMessage::HelloRetryRequest(hello))
+                } else {
+                    Some(HandshakeMessage::ServerHello(hello))
+                }
+            },
+            HandshakeType::Certificate => Some(HandshakeMessage::Certificate(Certificate::read(&mut body)?)),
+            // Key exchanges are told apart by their layout, which depends on the cipher suite.
+            // Those that fit no known layout are left unparsed.
+            HandshakeType::ServerKeyExchange => ServerKeyExchange::read(&body).map(HandshakeMessage::ServerKeyExchange),
+            HandshakeType::ServerHelloDone => Some(HandshakeMessage::ServerHelloDone),
+            HandshakeType::ClientKeyExchange => ClientKeyExchange::read(&body).map(HandshakeMessage::ClientKeyExchange),
+            _ => None,
+        };
 
-        return Some(Handshake {
+        return Ok(Handshake {
             msg_type: msg_type,
             length: length,
             msg: msg,
         })
     }
+
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties: Vec<(String, String)> = Vec::<(String, String)>::new();
+        properties.push((String::from("handshake"), format!("{:?} length={}", self.msg_type, self.length)));
+        match self.msg {
+            Some(HandshakeMessage::ClientHello(ref hello)) => {
+                properties.push((String::from("cipher_suites"), format!("{:?}", hello.cipher_suites)));
+                if let Some(server_name) = hello.server_name() {
+                    properties.push((String::from("server_name"), String::from(server_name)));
+                }
+                let fingerprint = Fingerprint::new(hello);
+                properties.push((String::from("ja3"), format!("{} ({})", fingerprint.ja3, fingerprint.ja3_string)));
+                properties.push((String::from("ja4"), fingerprint.ja4));
+            },
+            Some(HandshakeMessage::ServerHello(ref hello)) => {
+                properties.push((String::from("cipher_suite"), format!("{:?}", hello.cipher_suite)));
+            },
+            Some(HandshakeMessage::HelloRetryRequest(ref hello)) => {
+                properties.push((String::from("hello_retry_request"), format!("{:?}", hello.cipher_suite)));
+            },
+            Some(HandshakeMessage::Certificate(ref certificate)) => properties.extend(certificate.properties()),
+            Some(HandshakeMessage::ServerKeyExchange(ref key_exchange)) => properties.extend(key_exchange.properties()),
+            Some(HandshakeMessage::ClientKeyExchange(ref key_exchange)) => {
+                properties.push((String::from("client_key_exchange"), key_exchange.to_string()));
+            },
+            Some(HandshakeMessage::ServerHelloDone) | None => {},
+        };
+        if let Some(ref msg) = self.msg {
+            if !msg.extensions().is_empty() {
+                properties.push((String::from("extensions"), format!("{:?}", msg.extensions())));
+            }
+        }
+        return properties;
+    }
+
+    // The message type followed by the message, if it was parsed.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let msg = self.msg.as_ref()?.to_bytes()?;
+        let mut out = vec![self.msg_type.id()];
+        writer::vector(&mut out, 3, &msg)?;
+        return Some(out);
+    }
 }
 
 #[derive(Debug,PartialEq)]
@@ -87,9 +390,15 @@ pub enum HandshakeType {
     EndOfEarlyData,
     EncryptedExtensions,
     Certificate,
+    // RFC 5246 TLS 1.2 - Key exchange messages replaced by key shares in TLS 1.3.
+    ServerKeyExchange,
     CertificateRequest,
+    ServerHelloDone,
     CertificateVerify,
+    ClientKeyExchange,
     Finished,
+    // RFC 6066 8 - A stapled OCSP response, sent by TLS 1.2 servers after their certificate.
+    CertificateStatus,
     KeyUpdate,
     MessageHash,
 }
@@ -103,18 +412,93 @@ impl HandshakeType {
             5 => Some(HandshakeType::EndOfEarlyData),
             8 => Some(HandshakeType::EncryptedExtensions),
             11 => Some(HandshakeType::Certificate),
+            12 => Some(HandshakeType::ServerKeyExchange),
             13 => Some(HandshakeType::CertificateRequest),
+            14 => Some(HandshakeType::ServerHelloDone),
             15 => Some(HandshakeType::CertificateVerify),
+            16 => Some(HandshakeType::ClientKeyExchange),
             20 => Some(HandshakeType::Finished),
+            22 => Some(HandshakeType::CertificateStatus),
             24 => Some(HandshakeType::KeyUpdate),
             254 => Some(HandshakeType::MessageHash),
             _ => None,
         }
     }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            HandshakeType::ClientHello => 1,
+            HandshakeType::ServerHello => 2,
+            HandshakeType::NewSessionTicket => 4,
+            HandshakeType::EndOfEarlyData => 5,
+            HandshakeType::EncryptedExtensions => 8,
+            HandshakeType::Certificate => 11,
+            HandshakeType::ServerKeyExchange => 12,
+            HandshakeType::CertificateRequest => 13,
+            HandshakeType::ServerHelloDone => 14,
+            HandshakeType::CertificateVerify => 15,
+            HandshakeType::ClientKeyExchange => 16,
+            HandshakeType::Finished => 20,
+            HandshakeType::CertificateStatus => 22,
+            HandshakeType::KeyUpdate => 24,
+            HandshakeType::MessageHash => 254,
+        }
+    }
 }
 
+// RFC 8446 4.1.3 - A ServerHello with this random value is a HelloRetryRequest: the SHA-256 hash
+// of "HelloRetryRequest".
+pub const HELLO_RETRY_REQUEST_RANDOM: [u8; 0x20] = [
+    0xCF, 0x21, 0xAD, 0x74, 0xE5, 0x9A, 0x61, 0x11,
+    0xBE, 0x1D, 0x8C, 0x02, 0x1E, 0x65, 0xB8, 0x91,
+    0xC2, 0xA2, 0x11, 0x16, 0x7A, 0xBB, 0x8C, 0x5E,
+    0x07, 0x9E, 0x09, 0xE2, 0xC8, 0xA8, 0x33, 0x9C,
+];
+
 #[derive(Debug,PartialEq)]
-pub struct HandshakeMessage {
+pub enum HandshakeMessage {
+    ClientHello(ClientHello),
+    ServerHello(ServerHello),
+    // A ServerHello asking the client for another ClientHello, usually with a different key share.
+    HelloRetryRequest(ServerHello),
+    Certificate(Certificate),
+    ServerKeyExchange(ServerKeyExchange),
+    ServerHelloDone,
+    ClientKeyExchange(ClientKeyExchange),
+}
+
+impl HandshakeMessage {
+    // The first host name requested through the Server Name Indication extension.
+    pub fn server_name(&self) -> Option<&str> {
+        match self {
+            HandshakeMessage::ClientHello(hello) => hello.server_name(),
+            _ => None,
+        }
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        match self {
+            HandshakeMessage::ClientHello(hello) => &hello.extensions,
+            HandshakeMessage::ServerHello(hello) | HandshakeMessage::HelloRetryRequest(hello) => &hello.extensions,
+            _ => &[],
+        }
+    }
+
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            HandshakeMessage::ClientHello(hello) => hello.to_bytes(),
+            HandshakeMessage::ServerHello(hello) | HandshakeMessage::HelloRetryRequest(hello) => hello.to_bytes(),
+            HandshakeMessage::Certificate(certificate) => certificate.to_bytes(),
+            HandshakeMessage::ServerKeyExchange(key_exchange) => key_exchange.to_bytes(),
+            HandshakeMessage::ServerHelloDone => Some(Vec::new()),
+            HandshakeMessage::ClientKeyExchange(key_exchange) => key_exchange.to_bytes(),
+        }
+    }
+}
+
+// RFC 8446 4.1.2
+#[derive(Debug,PartialEq)]
+pub struct ClientHello {
     pub legacy_version: u16,
     pub random: [u8; 0x20],
     pub legacy_session_id: Vec::<u8>,
@@ -123,55 +507,452 @@ pub struct HandshakeMessage {
     pub extensions: Vec::<Extension>,
 }
 
-impl HandshakeMessage {
-    fn new(mut data: std::slice::Iter<u8>) -> Option<Self> {
-        let version: u16 = u16::from_be_bytes([*data.next()?, *data.next()?]);
+impl ClientHello {
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        Self::read(&mut Cursor::new(data))
+    }
+
+    fn read(data: &mut Cursor) -> Result<Self, ParseError> {
+        let version: u16 = data.u16("legacy_version")?;
         let mut random: [u8; 0x20] = [0; 0x20];
-        for i in 0..random.len() {
-            random[i] = *data.next()?;
-        }
+        random.copy_from_slice(data.bytes(0x20, "random")?);
 
         // Parse legacy session ID vector as a u8 length followed by n bytes
-        let legacy_session_id_length = data.next()?;
-        let mut legacy_session_id = Vec::<u8>::new();
-        for _ in 0..*legacy_session_id_length {
-            legacy_session_id.push(u8::clone(data.next()?));
-        }
+        let legacy_session_id = data.vector(1, "legacy_session_id")?.remaining().to_vec();
 
         // Parse cipher suites vector as a u16 length followed by n bytes
-        let cipher_suites_length: u16 = u16::from_be_bytes([*data.next()?, *data.next()?]);
+        let mut cipher_suites_data = data.vector(2, "cipher_suites")?;
+        if cipher_suites_data.remaining().len() % 2 != 0 {
+            return Err(ParseError::new(cipher_suites_data.offset() - 2, "cipher_suites", Reason::InvalidLength));
+        }
         let mut cipher_suites: Vec<CipherSuite> = Vec::new();
-        for _ in 0..cipher_suites_length/2 {
-            cipher_suites.push(CipherSuite::new(*data.next()?, *data.next()?));
+        while !cipher_suites_data.is_empty() {
+            let cipher_suite = cipher_suites_data.bytes(2, "cipher_suites")?;
+            cipher_suites.push(CipherSuite::new(cipher_suite[0], cipher_suite[1]));
         }
 
         // Parse legacy compression methods vector as a u8 length followed by n bytes
-        let legacy_compression_methods_length = *data.next()?;
-        let mut legacy_compression_methods = Vec::<u8>::new();
-        for _ in 0..legacy_compression_methods_length {
-            legacy_compression_methods.push(u8::clone(data.next()?));
-        }
-
-        // Parse extensions vector as a u16 length followed by n bytes
-        let _extensions_length: u16 = u16::from_be_bytes([*data.next()?, *data.next()?]);
-        let mut data = data.peekable();
-        let mut extensions = Vec::<Extension>::new();
-        while data.peek().is_some() {
-            let extension = Extension::new(&mut data);
-            match extension {
-                Ok(extension) => extensions.push(extension),
-                Err(e) => println!("Unrecognized extension ID: {e:?}"),
-            };
-        }
+        let legacy_compression_methods = data.vector(1, "legacy_compression_methods")?.remaining().to_vec();
 
-        return Some(HandshakeMessage {
+        return Ok(ClientHello {
             legacy_version: version,
             random: random,
             legacy_session_id: legacy_session_id,
             cipher_suites: cipher_suites,
             legacy_compression_methods: legacy_compression_methods,
-            extensions: extensions,
+            extensions: read_extensions(data, ExtensionContext::ClientHello)?,
+        });
+    }
+
+    // The first host name requested through the Server Name Indication extension.
+    pub fn server_name(&self) -> Option<&str> {
+        self.extensions.iter()
+            .find_map(|extension| match extension {
+                Extension::ServerName(list) => list.host_names.first(),
+                _ => None,
+            })
+            .map(|host_name| host_name.as_str())
+    }
+
+    // The hello as sent. None if a field is too long for its length prefix.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut out = self.legacy_version.to_be_bytes().to_vec();
+        out.extend_from_slice(&self.random);
+        writer::vector(&mut out, 1, &self.legacy_session_id)?;
+        let cipher_suites: Vec<u8> = self.cipher_suites.iter().flat_map(|cipher_suite| cipher_suite.id().to_be_bytes()).collect();
+        writer::vector(&mut out, 2, &cipher_suites)?;
+        writer::vector(&mut out, 1, &self.legacy_compression_methods)?;
+        write_extensions(&mut out, &self.extensions)?;
+        return Some(out);
+    }
+
+    // The ClientHello in handshake records, as sent first by clients. Hellos longer than a record
+    // can hold are split across records.
+    pub fn to_record(&self) -> Option<Vec<u8>> {
+        let mut handshake = vec![HandshakeType::ClientHello.id()];
+        writer::vector(&mut handshake, 3, &self.to_bytes()?)?;
+        let mut out = Vec::new();
+        for fragment in handshake.chunks(MAX_FRAGMENT_LENGTH) {
+            // RFC 8446 5.1 - Clients send their first ClientHello with the TLS 1.0 record version.
+            out.extend_from_slice(&[RecordContentType::Handshake.id(), 0x03, 0x01]);
+            writer::vector(&mut out, 2, fragment)?;
+        }
+        return Some(out);
+    }
+}
+
+// Constructs a ClientHello to send, such as a captured one with changes. Starts as a TLS 1.2
+// hello with no cipher suites or extensions and a zero random; extensions are written in the order
+// added.
+pub struct ClientHelloBuilder {
+    hello: ClientHello,
+}
+
+impl Default for ClientHelloBuilder {
+    fn default() -> Self {
+        ClientHelloBuilder::new()
+    }
+}
+
+impl ClientHelloBuilder {
+    pub fn new() -> Self {
+        ClientHelloBuilder {
+            hello: ClientHello {
+                legacy_version: 0x0303,
+                random: [0; 0x20],
+                legacy_session_id: Vec::new(),
+                cipher_suites: Vec::new(),
+                // RFC 8446 4.1.2 - Only the null compression method.
+                legacy_compression_methods: vec![0],
+                extensions: Vec::new(),
+            },
+        }
+    }
+
+    // Start from an existing hello, such as one parsed from a capture.
+    pub fn from_hello(hello: ClientHello) -> Self {
+        ClientHelloBuilder { hello: hello }
+    }
+
+    pub fn version(mut self, version: u16) -> Self {
+        self.hello.legacy_version = version;
+        self
+    }
+
+    pub fn random(mut self, random: [u8; 0x20]) -> Self {
+        self.hello.random = random;
+        self
+    }
+
+    pub fn session_id(mut self, session_id: &[u8]) -> Self {
+        self.hello.legacy_session_id = session_id.to_vec();
+        self
+    }
+
+    pub fn cipher_suite(mut self, cipher_suite: CipherSuite) -> Self {
+        self.hello.cipher_suites.push(cipher_suite);
+        self
+    }
+
+    pub fn cipher_suites(mut self, cipher_suites: Vec<CipherSuite>) -> Self {
+        self.hello.cipher_suites = cipher_suites;
+        self
+    }
+
+    // Add the extension, replacing any extension of the same type in its place.
+    pub fn extension(mut self, extension: Extension) -> Self {
+        match self.hello.extensions.iter().position(|existing| existing.id() == extension.id()) {
+            Some(i) => self.hello.extensions[i] = extension,
+            None => self.hello.extensions.push(extension),
+        };
+        self
+    }
+
+    pub fn without_extension(mut self, id: u16) -> Self {
+        self.hello.extensions.retain(|extension| extension.id() != id);
+        self
+    }
+
+    pub fn server_name(self, host_name: &str) -> Self {
+        self.extension(Extension::ServerName(ServerNameList { host_names: vec![host_name.to_string()] }))
+    }
+
+    pub fn alpn(self, protocols: &[&str]) -> Self {
+        let protocols = protocols.iter().map(|protocol| protocol.to_string()).collect();
+        self.extension(Extension::ApplicationLayerProtocolNegotiation(ALPN { protocol_name_list: protocols }))
+    }
+
+    // Add a key share to the key share extension, adding the extension if there is none.
+    pub fn key_share(mut self, group: NamedGroup, key_exchange: Vec<u8>) -> Self {
+        let entry = KeyShareEntry { group: group, key_exchange: key_exchange };
+        for extension in self.hello.extensions.iter_mut() {
+            if let Extension::KeyShare(KeyShare::ClientShares(shares)) = extension {
+                shares.push(entry);
+                return self;
+            }
+        }
+        self.extension(Extension::KeyShare(KeyShare::ClientShares(vec![entry])))
+    }
+
+    pub fn build(self) -> ClientHello {
+        self.hello
+    }
+}
+
+// RFC 8446 4.1.3 - Also the structure of a HelloRetryRequest.
+#[derive(Debug,PartialEq)]
+pub struct ServerHello {
+    pub legacy_version: u16,
+    pub random: [u8; 0x20],
+    pub legacy_session_id_echo: Vec::<u8>,
+    pub cipher_suite: CipherSuite,
+    pub legacy_compression_method: u8,
+    pub extensions: Vec::<Extension>,
+}
+
+impl ServerHello {
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        Self::read(&mut Cursor::new(data))
+    }
+
+    fn read(data: &mut Cursor) -> Result<Self, ParseError> {
+        let version: u16 = data.u16("legacy_version")?;
+        let mut random: [u8; 0x20] = [0; 0x20];
+        random.copy_from_slice(data.bytes(0x20, "random")?);
+        let legacy_session_id_echo = data.vector(1, "legacy_session_id_echo")?.remaining().to_vec();
+        let cipher_suite = data.bytes(2, "cipher_suite")?;
+        let cipher_suite = CipherSuite::new(cipher_suite[0], cipher_suite[1]);
+        let legacy_compression_method = data.u8("legacy_compression_method")?;
+        let context = if random == HELLO_RETRY_REQUEST_RANDOM { ExtensionContext::HelloRetryRequest } else { ExtensionContext::ServerHello };
+
+        return Ok(ServerHello {
+            legacy_version: version,
+            random: random,
+            legacy_session_id_echo: legacy_session_id_echo,
+            cipher_suite: cipher_suite,
+            legacy_compression_method: legacy_compression_method,
+            extensions: read_extensions(data, context)?,
+        });
+    }
+
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut out = self.legacy_version.to_be_bytes().to_vec();
+        out.extend_from_slice(&self.random);
+        writer::vector(&mut out, 1, &self.legacy_session_id_echo)?;
+        out.extend_from_slice(&self.cipher_suite.id().to_be_bytes());
+        out.push(self.legacy_compression_method);
+        write_extensions(&mut out, &self.extensions)?;
+        return Some(out);
+    }
+}
+
+// RFC 5246 7.4.2 - The certificate chain of the server, or of a client asked for one, each
+// certificate DER encoded and preceded by its length. TLS 1.3 sends it encrypted.
+#[derive(Debug,PartialEq)]
+pub struct Certificate {
+    pub certificate_list: Vec<Vec<u8>>,
+}
+
+impl Certificate {
+    fn read(data: &mut Cursor) -> Result<Self, ParseError> {
+        let mut certificate_list = data.vector(3, "certificate_list")?;
+        let mut certificates = Vec::new();
+        while !certificate_list.is_empty() {
+            certificates.push(certificate_list.vector(3, "ASN.1Cert")?.remaining().to_vec());
+        }
+        return Ok(Certificate {
+            certificate_list: certificates,
         });
     }
+
+    // Each certificate decoded, or why it could not be. Offsets in errors are from the start of
+    // the certificate.
+    pub fn x509(&self) -> Vec<Result<x509::Certificate, ParseError>> {
+        self.certificate_list.iter().map(|certificate| x509::Certificate::new(certificate)).collect()
+    }
+
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties: Vec<(String, String)> = Vec::<(String, String)>::new();
+        for (i, certificate) in self.x509().into_iter().enumerate() {
+            let certificate = match certificate {
+                Ok(certificate) => certificate,
+                Err(error) => {
+                    properties.push((String::from("certificate"), format!("#{} {}", i, error)));
+                    continue;
+                },
+            };
+            properties.push((String::from("certificate"), format!("#{} subject={}", i, certificate.subject)));
+            properties.push((String::from("issuer"), certificate.issuer));
+            if !certificate.subject_alt_names.is_empty() {
+                properties.push((String::from("subject_alt_names"), certificate.subject_alt_names.join(", ")));
+            }
+            properties.push((String::from("validity"), format!("{} to {}", certificate.not_before, certificate.not_after)));
+            properties.push((String::from("public_key"), certificate.public_key.to_string()));
+            properties.push((String::from("signature_algorithm"), certificate.signature_algorithm));
+        }
+        return properties;
+    }
+
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut certificates = Vec::new();
+        for certificate in self.certificate_list.iter() {
+            writer::vector(&mut certificates, 3, certificate)?;
+        }
+        let mut out = Vec::new();
+        writer::vector(&mut out, 3, &certificates)?;
+        return Some(out);
+    }
+}
+
+// RFC 5246 7.4.3 - The ephemeral key of the server, signed with the key of its certificate.
+#[derive(Debug,PartialEq)]
+pub enum ServerKeyExchange {
+    // RFC 8422 5.4 - Only named curves are allowed.
+    ECDHE { named_curve: NamedGroup, public: Vec<u8>, signature: DigitallySigned },
+    DHE { p: Vec<u8>, g: Vec<u8>, public: Vec<u8>, signature: DigitallySigned },
 }
 
+impl ServerKeyExchange {
+    // The parameters, if they fit either layout exactly.
+    fn read(data: &Cursor) -> Option<Self> {
+        return Self::read_ecdhe(&mut data.clone()).or_else(|_| Self::read_dhe(&mut data.clone())).ok();
+    }
+
+    fn read_ecdhe(data: &mut Cursor) -> Result<Self, ParseError> {
+        // ECCurveType named_curve
+        if data.u8("curve_type")? != 3 {
+            return Err(data.error("curve_type", Reason::Malformed));
+        }
+        let named_curve = NamedGroup::new(data.u16("namedcurve")?);
+        let public = data.vector(1, "point")?.remaining().to_vec();
+        let signature = DigitallySigned::read(data)?;
+        return Ok(ServerKeyExchange::ECDHE { named_curve: named_curve, public: public, signature: signature });
+    }
+
+    fn read_dhe(data: &mut Cursor) -> Result<Self, ParseError> {
+        let p = data.vector(2, "dh_p")?.remaining().to_vec();
+        let g = data.vector(2, "dh_g")?.remaining().to_vec();
+        let public = data.vector(2, "dh_Ys")?.remaining().to_vec();
+        let signature = DigitallySigned::read(data)?;
+        return Ok(ServerKeyExchange::DHE { p: p, g: g, public: public, signature: signature });
+    }
+
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties: Vec<(String, String)> = Vec::<(String, String)>::new();
+        let signature = match self {
+            ServerKeyExchange::ECDHE { named_curve, public, signature } => {
+                properties.push((String::from("server_key_exchange"), format!("ECDHE {:?} public={} bytes", named_curve, public.len())));
+                signature
+            },
+            ServerKeyExchange::DHE { p, g, public, signature } => {
+                properties.push((String::from("server_key_exchange"), format!("DHE p={} bits g={:02X?} public={} bytes", der::bit_length(p), g, public.len())));
+                signature
+            },
+        };
+        properties.push((String::from("signature"), signature.to_string()));
+        return properties;
+    }
+
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        match self {
+            ServerKeyExchange::ECDHE { named_curve, public, signature } => {
+                out.push(3);
+                out.extend_from_slice(&named_curve.id().to_be_bytes());
+                writer::vector(&mut out, 1, public)?;
+                signature.write(&mut out)?;
+            },
+            ServerKeyExchange::DHE { p, g, public, signature } => {
+                writer::vector(&mut out, 2, p)?;
+                writer::vector(&mut out, 2, g)?;
+                writer::vector(&mut out, 2, public)?;
+                signature.write(&mut out)?;
+            },
+        };
+        return Some(out);
+    }
+}
+
+// RFC 5246 4.7 - A signature and, from TLS 1.2, the algorithm used.
+#[derive(Debug,PartialEq)]
+pub struct DigitallySigned {
+    pub algorithm: Option<SignatureScheme>,
+    pub signature: Vec<u8>,
+}
+
+impl DigitallySigned {
+    // Reads the rest of the data, which must hold exactly the signature.
+    fn read(data: &mut Cursor) -> Result<Self, ParseError> {
+        let rest = data.remaining();
+        let algorithm = match rest {
+            [b0, b1, ..] if usize::from(u16::from_be_bytes([*b0, *b1])) == rest.len() - 2 => None,
+            _ => Some(SignatureScheme::new(data.u16("algorithm")?)),
+        };
+        let signature = data.vector(2, "signature")?.remaining().to_vec();
+        if !data.is_empty() {
+            return Err(data.error("signature", Reason::InvalidLength));
+        }
+        return Ok(DigitallySigned { algorithm: algorithm, signature: signature });
+    }
+
+    fn write(&self, out: &mut Vec<u8>) -> Option<()> {
+        if let Some(ref algorithm) = self.algorithm {
+            out.extend_from_slice(&algorithm.id().to_be_bytes());
+        }
+        return writer::vector(out, 2, &self.signature);
+    }
+}
+
+impl fmt::Display for DigitallySigned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self.algorithm {
+            Some(ref algorithm) => write!(f, "{:?} {} bytes", algorithm, self.signature.len()),
+            None => write!(f, "{} bytes", self.signature.len()),
+        }
+    }
+}
+
+// RFC 5246 7.4.7 - The key exchange of the client, which depends on the cipher suite selected
+// by the server. Its length prefix tells ECDHE apart from the others.
+#[derive(Debug,PartialEq)]
+pub enum ClientKeyExchange {
+    // RFC 8422 5.7 - The ephemeral ECDH public key.
+    ECDHE(Vec<u8>),
+    // The premaster secret encrypted with the RSA key of the server, or the ephemeral DH public
+    // value. Both are as long as the key of the server.
+    RSAOrDHE(Vec<u8>),
+}
+
+impl ClientKeyExchange {
+    fn read(data: &Cursor) -> Option<Self> {
+        let rest = data.remaining();
+        match rest {
+            [length, ..] if usize::from(*length) == rest.len() - 1 => Some(ClientKeyExchange::ECDHE(rest[1..].to_vec())),
+            [b0, b1, ..] if usize::from(u16::from_be_bytes([*b0, *b1])) == rest.len() - 2 => Some(ClientKeyExchange::RSAOrDHE(rest[2..].to_vec())),
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        match self {
+            ClientKeyExchange::ECDHE(public) => writer::vector(&mut out, 1, public)?,
+            ClientKeyExchange::RSAOrDHE(exchange) => writer::vector(&mut out, 2, exchange)?,
+        };
+        return Some(out);
+    }
+}
+
+impl fmt::Display for ClientKeyExchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ClientKeyExchange::ECDHE(public) => write!(f, "ECDHE public={} bytes", public.len()),
+            ClientKeyExchange::RSAOrDHE(exchange) => write!(f, "RSA or DHE {} bytes", exchange.len()),
+        }
+    }
+}
+
+// Parse extensions vector as a u16 length followed by n bytes. Hellos without extensions end
+// before the vector.
+fn read_extensions(data: &mut Cursor, context: ExtensionContext) -> Result<Vec<Extension>, ParseError> {
+    let mut extensions = Vec::<Extension>::new();
+    if data.is_empty() {
+        return Ok(extensions);
+    }
+    let mut extensions_data = data.vector(2, "extensions")?;
+    while !extensions_data.is_empty() {
+        let extension_type = extensions_data.u16("extension_type")?;
+        let extension_data = extensions_data.vector(2, "extension_data")?;
+        extensions.push(Extension::new(extension_type, extension_data.remaining(), context));
+    }
+    return Ok(extensions);
+}
+
+// Write the extensions vector. It is left out of hellos without extensions, as when read.
+fn write_extensions(out: &mut Vec<u8>, extensions: &[Extension]) -> Option<()> {
+    if !extensions.is_empty() {
+        let extensions: Vec<Vec<u8>> = extensions.iter().map(|extension| extension.to_bytes()).collect::<Option<_>>()?;
+        writer::vector(out, 2, &extensions.concat())?;
+    }
+    return Some(());
+}
@@ -0,0 +1,42 @@
+// Writes the length-prefixed vectors read by Cursor.
+
+// RFC 8446 3.4 - Append the data preceded by its length, encoded in the given number of bytes.
+// Returns None, appending nothing, if the data is longer than the prefix can encode.
+pub fn vector(out: &mut Vec<u8>, length_bytes: usize, data: &[u8]) -> Option<()> {
+    let length = data.len() as u64;
+    if length >> (8 * length_bytes) != 0 {
+        return None;
+    }
+    out.extend_from_slice(&length.to_be_bytes()[8 - length_bytes..]);
+    out.extend_from_slice(data);
+    return Some(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::Cursor;
+
+    #[test]
+    fn vector_read_back() {
+        let mut out = Vec::new();
+        vector(&mut out, 1, &[0x01]).unwrap();
+        vector(&mut out, 2, &[0x02, 0x03]).unwrap();
+        vector(&mut out, 3, &[]).unwrap();
+        assert_eq!(out, vec![0x01, 0x01, 0x00, 0x02, 0x02, 0x03, 0x00, 0x00, 0x00]);
+
+        let mut cursor = Cursor::new(&out);
+        assert_eq!(cursor.vector(1, "a").unwrap().remaining(), &[0x01]);
+        assert_eq!(cursor.vector(2, "b").unwrap().remaining(), &[0x02, 0x03]);
+        assert!(cursor.vector(3, "c").unwrap().is_empty());
+    }
+
+    #[test]
+    fn vector_too_long() {
+        let mut out = Vec::new();
+        assert_eq!(vector(&mut out, 1, &[0xAA; 0xFF]), Some(()));
+        assert_eq!(vector(&mut out, 1, &[0xAA; 0x100]), None);
+        assert_eq!(vector(&mut out, 2, &[0xAA; 0x10000]), None);
+        assert_eq!(out.len(), 0x100);
+    }
+}
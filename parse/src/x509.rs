@@ -0,0 +1,262 @@
+use std::fmt;
+
+use crate::cursor::Cursor;
+use crate::der;
+use crate::error::{ParseError, Reason};
+
+// RFC 5280 4.1 - The parts of an X.509 certificate shown when inspecting a handshake. The
+// signature itself is not verified.
+#[derive(Debug,PartialEq)]
+pub struct Certificate {
+    // 1 to 3, for v1 to v3 certificates.
+    pub version: u8,
+    pub serial_number: Vec<u8>,
+    pub signature_algorithm: String,
+    pub issuer: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub subject: String,
+    pub public_key: PublicKey,
+    // DNS names, IP addresses, email addresses and URIs of the subjectAltName extension.
+    pub subject_alt_names: Vec<String>,
+}
+
+#[derive(Debug,PartialEq)]
+pub enum PublicKey {
+    // Size of the modulus in bits.
+    RSA(usize),
+    // Named curve, or its object identifier if not known.
+    EC(String),
+    Ed25519,
+    Ed448,
+    DSA,
+    // Object identifier of the algorithm.
+    Unknown(String),
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            PublicKey::RSA(bits) => write!(f, "RSA {} bits", bits),
+            PublicKey::EC(curve) => write!(f, "EC {}", curve),
+            PublicKey::Ed25519 => write!(f, "Ed25519"),
+            PublicKey::Ed448 => write!(f, "Ed448"),
+            PublicKey::DSA => write!(f, "DSA"),
+            PublicKey::Unknown(oid) => write!(f, "{}", oid),
+        }
+    }
+}
+
+impl Certificate {
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        let mut data = Cursor::new(data);
+        let mut certificate = der::expect(&mut data, der::SEQUENCE, "certificate")?;
+        let mut tbs = der::expect(&mut certificate, der::SEQUENCE, "tbsCertificate")?;
+        let signature_algorithm = algorithm_identifier(&mut certificate, "signatureAlgorithm")?;
+
+        // Version is an explicitly tagged [0] field, absent for v1.
+        let version = match der::optional(&mut tbs, 0xA0, "version")? {
+            Some(mut version) => {
+                let value = der::expect(&mut version, der::INTEGER, "version")?;
+                match value.remaining() {
+                    [n @ 0..=2] => n + 1,
+                    _ => return Err(value.error("version", Reason::Malformed)),
+                }
+            },
+            None => 1,
+        };
+        let serial_number = der::expect(&mut tbs, der::INTEGER, "serialNumber")?.remaining().to_vec();
+        // The signature field repeats signatureAlgorithm.
+        algorithm_identifier(&mut tbs, "signature")?;
+        let issuer = name(&mut tbs, "issuer")?;
+        let mut validity = der::expect(&mut tbs, der::SEQUENCE, "validity")?;
+        let not_before = der::time(&der::read(&mut validity, "notBefore")?, "notBefore")?;
+        let not_after = der::time(&der::read(&mut validity, "notAfter")?, "notAfter")?;
+        let subject = name(&mut tbs, "subject")?;
+        let public_key = subject_public_key_info(&mut tbs)?;
+
+        // The unique identifiers [1] and [2] are skipped, and extensions are an explicitly tagged
+        // [3] field.
+        let mut subject_alt_names = Vec::new();
+        while !tbs.is_empty() {
+            let element = der::read(&mut tbs, "extensions")?;
+            if element.tag != 0xA3 {
+                continue;
+            }
+            let mut contents = element.contents;
+            let mut extensions = der::expect(&mut contents, der::SEQUENCE, "extensions")?;
+            while !extensions.is_empty() {
+                let mut extension = der::expect(&mut extensions, der::SEQUENCE, "extension")?;
+                let id = der::object_identifier(&der::expect(&mut extension, der::OBJECT_IDENTIFIER, "extnID")?, "extnID")?;
+                der::optional(&mut extension, der::BOOLEAN, "critical")?;
+                let mut value = der::expect(&mut extension, der::OCTET_STRING, "extnValue")?;
+                if id == "2.5.29.17" {
+                    subject_alt_names = general_names(&mut value)?;
+                }
+            }
+        }
+
+        return Ok(Certificate {
+            version: version,
+            serial_number: serial_number,
+            signature_algorithm: signature_algorithm,
+            issuer: issuer,
+            not_before: not_before,
+            not_after: not_after,
+            subject: subject,
+            public_key: public_key,
+            subject_alt_names: subject_alt_names,
+        });
+    }
+}
+
+// RFC 5280 4.1.1.2 - The algorithm of an AlgorithmIdentifier, by name if known. Parameters are
+// skipped.
+fn algorithm_identifier(data: &mut Cursor, field: &'static str) -> Result<String, ParseError> {
+    let mut algorithm = der::expect(data, der::SEQUENCE, field)?;
+    let id = der::object_identifier(&der::expect(&mut algorithm, der::OBJECT_IDENTIFIER, field)?, field)?;
+    let name = match id.as_str() {
+        "1.2.840.113549.1.1.4" => "md5WithRSAEncryption",
+        "1.2.840.113549.1.1.5" => "sha1WithRSAEncryption",
+        "1.2.840.113549.1.1.10" => "RSASSA-PSS",
+        "1.2.840.113549.1.1.11" => "sha256WithRSAEncryption",
+        "1.2.840.113549.1.1.12" => "sha384WithRSAEncryption",
+        "1.2.840.113549.1.1.13" => "sha512WithRSAEncryption",
+        "1.2.840.113549.1.1.14" => "sha224WithRSAEncryption",
+        "1.2.840.10045.4.1" => "ecdsa-with-SHA1",
+        "1.2.840.10045.4.3.1" => "ecdsa-with-SHA224",
+        "1.2.840.10045.4.3.2" => "ecdsa-with-SHA256",
+        "1.2.840.10045.4.3.3" => "ecdsa-with-SHA384",
+        "1.2.840.10045.4.3.4" => "ecdsa-with-SHA512",
+        "1.2.840.10040.4.3" => "dsa-with-SHA1",
+        "2.16.840.1.101.3.4.3.2" => "dsa-with-SHA256",
+        "1.3.101.112" => "Ed25519",
+        "1.3.101.113" => "Ed448",
+        _ => return Ok(id),
+    };
+    return Ok(String::from(name));
+}
+
+// RFC 5280 4.1.2.4 - A Name as its attributes in order, such as "C=US, O=Example, CN=example.com".
+fn name(data: &mut Cursor, field: &'static str) -> Result<String, ParseError> {
+    let mut rdn_sequence = der::expect(data, der::SEQUENCE, field)?;
+    let mut attributes: Vec<String> = Vec::new();
+    while !rdn_sequence.is_empty() {
+        let mut rdn = der::expect(&mut rdn_sequence, der::SET, field)?;
+        while !rdn.is_empty() {
+            let mut attribute = der::expect(&mut rdn, der::SEQUENCE, field)?;
+            let id = der::object_identifier(&der::expect(&mut attribute, der::OBJECT_IDENTIFIER, field)?, field)?;
+            let value = der::string(&der::read(&mut attribute, field)?);
+            let short_name = match id.as_str() {
+                "2.5.4.3" => "CN",
+                "2.5.4.5" => "serialNumber",
+                "2.5.4.6" => "C",
+                "2.5.4.7" => "L",
+                "2.5.4.8" => "ST",
+                "2.5.4.9" => "street",
+                "2.5.4.10" => "O",
+                "2.5.4.11" => "OU",
+                "1.2.840.113549.1.9.1" => "emailAddress",
+                "0.9.2342.19200300.100.1.25" => "DC",
+                _ => id.as_str(),
+            };
+            attributes.push(format!("{}={}", short_name, value));
+        }
+    }
+    return Ok(attributes.join(", "));
+}
+
+// RFC 5280 4.1.2.7
+fn subject_public_key_info(data: &mut Cursor) -> Result<PublicKey, ParseError> {
+    let mut info = der::expect(data, der::SEQUENCE, "subjectPublicKeyInfo")?;
+    let mut algorithm = der::expect(&mut info, der::SEQUENCE, "algorithm")?;
+    let id = der::object_identifier(&der::expect(&mut algorithm, der::OBJECT_IDENTIFIER, "algorithm")?, "algorithm")?;
+    let mut key = der::expect(&mut info, der::BIT_STRING, "subjectPublicKey")?;
+    let public_key = match id.as_str() {
+        // RFC 8017 A.1.1 - The key is an RSAPublicKey, whose modulus gives the size.
+        "1.2.840.113549.1.1.1" => {
+            key.u8("subjectPublicKey")?;
+            let mut rsa_key = der::expect(&mut key, der::SEQUENCE, "RSAPublicKey")?;
+            let modulus = der::expect(&mut rsa_key, der::INTEGER, "modulus")?;
+            PublicKey::RSA(der::bit_length(modulus.remaining()))
+        },
+        // RFC 5480 2.1.1 - The named curve is the algorithm parameter.
+        "1.2.840.10045.2.1" => {
+            let curve = der::object_identifier(&der::expect(&mut algorithm, der::OBJECT_IDENTIFIER, "namedCurve")?, "namedCurve")?;
+            let curve = match curve.as_str() {
+                "1.2.840.10045.3.1.7" => String::from("P-256"),
+                "1.3.132.0.34" => String::from("P-384"),
+                "1.3.132.0.35" => String::from("P-521"),
+                "1.3.132.0.10" => String::from("secp256k1"),
+                _ => curve,
+            };
+            PublicKey::EC(curve)
+        },
+        "1.3.101.112" => PublicKey::Ed25519,
+        "1.3.101.113" => PublicKey::Ed448,
+        "1.2.840.10040.4.1" => PublicKey::DSA,
+        _ => PublicKey::Unknown(id),
+    };
+    return Ok(public_key);
+}
+
+// RFC 5280 4.2.1.6 - The names of a GeneralNames sequence that are text or addresses. Directory
+// names and other names are left out.
+fn general_names(data: &mut Cursor) -> Result<Vec<String>, ParseError> {
+    let mut general_names = der::expect(data, der::SEQUENCE, "subjectAltName")?;
+    let mut names = Vec::new();
+    while !general_names.is_empty() {
+        let element = der::read(&mut general_names, "GeneralName")?;
+        let value = element.contents.remaining();
+        match element.tag {
+            // rfc822Name, dNSName and uniformResourceIdentifier are IA5Strings.
+            0x81 | 0x82 | 0x86 => names.push(String::from_utf8_lossy(value).to_string()),
+            0x87 => match value.len() {
+                4 => names.push(std::net::Ipv4Addr::from([value[0], value[1], value[2], value[3]]).to_string()),
+                16 => {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(value);
+                    names.push(std::net::Ipv6Addr::from(octets).to_string());
+                },
+                _ => return Err(ParseError::new(element.offset, "iPAddress", Reason::InvalidLength)),
+            },
+            _ => {},
+        };
+    }
+    return Ok(names);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A DER element with a short form length.
+    fn element(tag: u8, contents: &[u8]) -> Vec<u8> {
+        return [&[tag, contents.len() as u8], contents].concat();
+    }
+
+    #[test]
+    fn rsa_key_size() {
+        // A 512-bit modulus, preceded by a zero byte to keep it positive.
+        let modulus = [&[0x00, 0x80][..], &[0x01; 0x3F]].concat();
+        let rsa_key = element(der::SEQUENCE, &[element(der::INTEGER, &modulus), element(der::INTEGER, &[0x03])].concat());
+        let algorithm = element(der::SEQUENCE, &[
+            element(der::OBJECT_IDENTIFIER, &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01]),
+            vec![0x05, 0x00]].concat());
+        let info = element(der::SEQUENCE, &[algorithm, element(der::BIT_STRING, &[&[0x00], &rsa_key[..]].concat())].concat());
+        assert_eq!(subject_public_key_info(&mut Cursor::new(&info)), Ok(PublicKey::RSA(512)));
+    }
+
+    #[test]
+    fn alt_names() {
+        let names = element(der::SEQUENCE, &[
+            element(0x82, b"example.com"),
+            element(0x87, &[0x7F, 0x00, 0x00, 0x01]),
+            element(0x87, &[0x00; 16]),
+            element(0x81, b"admin@example.com"),
+            element(0xA4, &[0x30, 0x00])].concat());
+        assert_eq!(general_names(&mut Cursor::new(&names)).unwrap(), vec!["example.com", "127.0.0.1", "::", "admin@example.com"]);
+        let names = element(der::SEQUENCE, &element(0x87, &[0x7F, 0x00, 0x00]));
+        assert_eq!(general_names(&mut Cursor::new(&names)), Err(ParseError::new(2, "iPAddress", Reason::InvalidLength)));
+    }
+}
@@ -1,7 +1,14 @@
 mod tests {
-    use parse::tls_request::{Record, RecordContentType, Handshake, HandshakeType, HandshakeMessage};
+    use parse::Request;
+    use parse::flow::Flow;
+    use parse::fingerprint::Fingerprint;
+    use parse::error::{ParseError, Reason};
+    use parse::http_request::HTTPRequest;
+    use parse::tls_request::{Record, RecordReader, ClientHelloBuilder, Message, RecordContentType, Handshake, HandshakeType, HandshakeMessage, ClientHello, ServerHello, Certificate, ServerKeyExchange, ClientKeyExchange, DigitallySigned, HELLO_RETRY_REQUEST_RANDOM};
     use parse::tls_cipher_suite::CipherSuite;
-    use parse::tls_extension::{Extension, ALPN, SignatureScheme, NamedGroup};
+    use parse::x509::PublicKey;
+    use parse::tls_alert::{Alert, AlertLevel, AlertDescription};
+    use parse::tls_extension::{Extension, ALPN, ServerNameList, SignatureScheme, NamedGroup, ECPointFormat, SupportedVersions, ProtocolVersion, PSKKeyExchangeMode, KeyShare, KeyShareEntry, PreSharedKey, PSKIdentity, MaxFragmentLength, CertificateStatusRequest};
 
     #[test]
     fn tls_hello() {
@@ -78,7 +85,7 @@ mod tests {
             fragment: Some(Handshake {
                 msg_type: HandshakeType::ClientHello,
                 length: 0x1FC,
-                msg: Some(HandshakeMessage {
+                msg: Some(HandshakeMessage::ClientHello(ClientHello {
                     legacy_version: 0x0303,
                     random: [
                         0x3C, 0xE1, 0x9E, 0x29, 0xAB, 0x07, 0x7E, 0x8B,
@@ -184,7 +191,11 @@ mod tests {
                         CipherSuite::TLSEmptyRenegotiationInfoSCSV,
                     ],
                     legacy_compression_methods: vec![0x00],
-                    extensions: vec![Extension::ECPointFormats,
+                    extensions: vec![Extension::ECPointFormats(vec![
+                                        ECPointFormat::Uncompressed,
+                                        ECPointFormat::ANSIX962CompressedPrime,
+                                        ECPointFormat::ANSIX962CompressedChar2,
+                                    ]),
                                     Extension::SupportedGroups(vec![
                                         NamedGroup::X25519,
                                         NamedGroup::SECP256R1,
@@ -195,6 +206,7 @@ mod tests {
                                         NamedGroup::FFDHE3072,
                                         NamedGroup::FFDHE4096,
                                         NamedGroup::FFDHE6144,
+                                        NamedGroup::FFDHE8192,
                                     ]),
                                     Extension::ApplicationLayerProtocolNegotiation(ALPN { protocol_name_list: vec!["h2".to_string(), "http/1.1".to_string()] }),
                                     Extension::EncryptThenMAC,
@@ -222,15 +234,568 @@ mod tests {
                                         SignatureScheme::SHA224DSA,
                                         SignatureScheme::SHA1DSA,
                                         SignatureScheme::SHA256DSA,
-                                        SignatureScheme::SHA384DSA
+                                        SignatureScheme::SHA384DSA,
+                                        SignatureScheme::SHA512DSA,
                                     ]),
-                                    Extension::SupportedVersions,
-                                    Extension::PSKKeyExchangeModes,
-                                    Extension::KeyShare,
-                                    Extension::Padding],
-                }),
+                                    Extension::SupportedVersions(SupportedVersions::Offered(vec![
+                                        ProtocolVersion::TLS13,
+                                        ProtocolVersion::TLS12,
+                                        ProtocolVersion::TLS11,
+                                        ProtocolVersion::TLS10,
+                                    ])),
+                                    Extension::PSKKeyExchangeModes(vec![PSKKeyExchangeMode::PSKDHEKE]),
+                                    Extension::KeyShare(KeyShare::ClientShares(vec![KeyShareEntry {
+                                        group: NamedGroup::X25519,
+                                        key_exchange: vec![
+                                            0x3F, 0xD6, 0x40, 0xC8, 0x78, 0x04, 0x9D, 0x3B,
+                                            0xBA, 0x37, 0x19, 0xE2, 0xCE, 0xB4, 0x5B, 0xA2,
+                                            0x45, 0x70, 0x2C, 0xE5, 0xE8, 0xE3, 0x3E, 0x12,
+                                            0x27, 0xAC, 0xA5, 0x75, 0xB9, 0xAB, 0xB1, 0x16],
+                                    }])),
+                                    Extension::Padding(72)],
+                })),
             }),
+            alert: None,
+        };
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.to_bytes().unwrap(), request.to_vec());
+    }
+
+    #[test]
+    fn tls_hello_server_name() {
+        let request: [u8; 0x48] = [0x16, 0x03, 0x01, 0x00, 0x43, 0x01, 0x00, 0x00,
+            0x3F, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x13, 0x01,
+            0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x10,
+            0x00, 0x0E, 0x00, 0x00, 0x0B, 0x65, 0x78, 0x61,
+            0x6D, 0x70, 0x6C, 0x65, 0x2E, 0x63, 0x6F, 0x6D];
+        let parsed = Record::new(&request).unwrap();
+        let msg = parsed.fragment.unwrap().msg.unwrap();
+        assert_eq!(msg.extensions(), vec![Extension::ServerName(ServerNameList { host_names: vec!["example.com".to_string()] })]);
+        assert_eq!(msg.server_name(), Some("example.com"));
+    }
+
+    #[test]
+    fn tls_round_trip() {
+        // Records are written back byte for byte, whichever the extensions and message.
+        let mut captures = vec![server_hello([0x11; 0x20]), server_hello(HELLO_RETRY_REQUEST_RANDOM)];
+        let mut hello = vec![0x16, 0x03, 0x01, 0x00, 0x45, 0x01, 0x00, 0x00, 0x41, 0x03, 0x03];
+        hello.extend_from_slice(&[0x00; 0x20]);
+        hello.extend_from_slice(&[0x00, 0x00, 0x04, 0x1A, 0x1A, 0x13, 0x01, 0x01, 0x00, 0x00, 0x14]);
+        hello.extend_from_slice(&[
+            0x2A, 0x2A, 0x00, 0x01, 0x00,
+            0x00, 0x2B, 0x00, 0x05, 0x04, 0x4A, 0x4A, 0x03, 0x04,
+            0xFE, 0x0D, 0x00, 0x02, 0xAB, 0xCD]);
+        captures.push(hello);
+        // A hello without extensions.
+        let mut hello = vec![0x16, 0x03, 0x01, 0x00, 0x2D, 0x01, 0x00, 0x00, 0x29, 0x03, 0x03];
+        hello.extend_from_slice(&[0x22; 0x20]);
+        hello.extend_from_slice(&[0x00, 0x00, 0x02, 0xC0, 0x2F, 0x01, 0x00]);
+        captures.push(hello);
+        for capture in captures {
+            let parsed = Record::new(&capture).unwrap();
+            assert_eq!(parsed.to_bytes().unwrap(), capture);
+        }
+    }
+
+    #[test]
+    fn tls_client_hello_builder() {
+        let hello = ClientHelloBuilder::new()
+            .random([0x33; 0x20])
+            .session_id(&[0x01, 0x02])
+            .cipher_suite(CipherSuite::TLSAES128GCMSHA256)
+            .cipher_suite(CipherSuite::TLSChaCha20Poly1305SHA256)
+            .server_name("example.com")
+            .alpn(&["h2", "http/1.1"])
+            .extension(Extension::SupportedVersions(SupportedVersions::Offered(vec![ProtocolVersion::TLS13])))
+            .key_share(NamedGroup::X25519, vec![0xAA; 0x20])
+            .key_share(NamedGroup::SECP256R1, vec![0xBB; 0x41])
+            .server_name("example.org")
+            .build();
+        assert_eq!(hello.server_name(), Some("example.org"));
+        assert_eq!(hello.extensions.len(), 4);
+
+        // The record parses back to the same hello.
+        let record = hello.to_record().unwrap();
+        assert_eq!(&record[..3], &[0x16, 0x03, 0x01]);
+        assert_eq!(Record::new(&record).unwrap().fragment.unwrap().msg, Some(HandshakeMessage::ClientHello(hello)));
+
+        // Changing a captured hello leaves the rest of it as captured.
+        let parsed = match Record::new(&record).unwrap().fragment.unwrap().msg {
+            Some(HandshakeMessage::ClientHello(hello)) => hello,
+            msg => panic!("expected a ClientHello, found {:?}", msg),
+        };
+        let changed = ClientHelloBuilder::from_hello(parsed).without_extension(0x0010).server_name("example.net").build();
+        assert_eq!(changed.server_name(), Some("example.net"));
+        assert_eq!(changed.extensions.len(), 3);
+        assert_eq!(changed.random, [0x33; 0x20]);
+
+        // Hellos longer than a record can hold are split across records. Fields too long for
+        // their length prefix cannot be written.
+        let hello = ClientHelloBuilder::new().key_share(NamedGroup::X25519, vec![0xAA; 0x5000]).build();
+        let record = hello.to_record().unwrap();
+        let records: Vec<_> = RecordReader::new(&record).map(|record| record.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].length, 0x4000);
+        match records[1].messages[..] {
+            [Message::Handshake(ref handshake)] => assert_eq!(handshake.msg, Some(HandshakeMessage::ClientHello(hello))),
+            _ => panic!("expected a ClientHello"),
         };
-        assert_eq!(parsed, expected)
+        assert_eq!(ClientHelloBuilder::new().session_id(&[0x00; 0x100]).build().to_record(), None);
+        assert_eq!(ClientHelloBuilder::new().key_share(NamedGroup::X25519, vec![0xAA; 0x10000]).build().to_record(), None);
+    }
+
+    #[test]
+    fn flow_response() {
+        let mut flow = Flow::new(1, 1, "127.0.0.1:50000".parse().unwrap(), Request::new(100, b"GET / HTTP/1.1\r\n\r\n".to_vec()));
+        flow.connected(110, Some("127.0.0.1:80".parse().unwrap()));
+        flow.extend_response(120, b"HTTP/1.1 200 OK\r\n");
+        flow.extend_response(130, b"\r\n");
+        flow.complete(150);
+        assert_eq!(flow.response.as_ref().unwrap().data(), b"HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(flow.timings.response_start, Some(120));
+        assert_eq!(flow.to_string(), "#1 connection 1 127.0.0.1:50000 -> 127.0.0.1:80 (50 ms)");
+    }
+
+    #[test]
+    fn http_request_round_trip() {
+        let requests: [&[u8]; 4] = [
+            b"GET /index.html?q=1 HTTP/1.1\r\nHost: example.com\r\nuser-agent: curl/8.0\r\nACCEPT: */*\r\n\r\n",
+            b"POST /form HTTP/1.1\r\nHost: example.com\r\nContent-Length: 12\r\n\r\nA=1\r\nB=2\n\nC",
+            b"PUT /upload HTTP/1.0\r\nX-Empty: \r\nCookie: a=1\r\nCookie: b=2\r\n\r\n\r\n\r\n",
+            b"OPTIONS * HTTP/1.1\r\n\r\n",
+        ];
+        for request in requests {
+            let parsed = HTTPRequest::new(request).unwrap();
+            assert_eq!(parsed.to_bytes(), request.to_vec(), "{}", String::from_utf8_lossy(request));
+        }
+    }
+
+    #[test]
+    fn http_request_edit() {
+        let mut request = HTTPRequest::new(b"POST /a HTTP/1.1\r\nhost: example.com\r\nX-Trace: 1\r\ncontent-length: 1\r\nX-Trace: 2\r\n\r\nA").unwrap();
+        request.set_method("PUT");
+        request.set_target("/b");
+        request.set_header("X-Trace", "3");
+        request.add_header("Accept", "*/*");
+        assert!(request.remove_header("HOST"));
+        request.set_body(b"A\r\nB".to_vec());
+        request.update_content_length();
+        assert_eq!(request.to_bytes(), b"PUT /b HTTP/1.1\r\nX-Trace: 3\r\ncontent-length: 4\r\nAccept: */*\r\n\r\nA\r\nB".to_vec());
+
+        let mut chunked = HTTPRequest::new(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n").unwrap();
+        chunked.update_content_length();
+        assert_eq!(chunked.header("Content-Length"), None);
+    }
+
+    #[test]
+    fn tls_parse_errors() {
+        assert_eq!(Record::new(&[0x63, 0x03, 0x03, 0x00, 0x00]), Err(ParseError::new(0, "type", Reason::UnknownType(0x63))));
+        assert_eq!(Record::new(&[0x16, 0x03, 0x01, 0x00, 0x10, 0x01]), Err(ParseError::new(5, "fragment", Reason::Truncated)));
+
+        // A ClientHello whose cipher suites vector claims more bytes than the message contains.
+        let mut hello = vec![0x16, 0x03, 0x01, 0x00, 0x2B, 0x01, 0x00, 0x00, 0x27, 0x03, 0x03];
+        hello.extend_from_slice(&[0x00; 0x20]);
+        hello.extend_from_slice(&[0x00, 0x00, 0x10, 0x13, 0x01]);
+        assert_eq!(Record::new(&hello), Err(ParseError::new(44, "cipher_suites", Reason::InvalidLength)));
+    }
+
+    #[test]
+    fn http_request_parse_errors() {
+        assert_eq!(HTTPRequest::new(b"GET / HTTP/1.1\r\nHost: \xFF\r\n\r\n"), Err(ParseError::new(22, "field-line", Reason::InvalidUtf8)));
+        assert_eq!(HTTPRequest::new(b"GET / HTTP/1.1\r\nHost\r\n\r\n"), Err(ParseError::new(16, "field-line", Reason::Malformed)));
+        assert_eq!(HTTPRequest::new(b"GET /\r\n\r\n"), Err(ParseError::new(5, "request-line", Reason::Malformed)));
+        assert_eq!(HTTPRequest::new(b"GET / HTTP/1.1 junk\r\n\r\n"), Err(ParseError::new(15, "request-line", Reason::Malformed)));
+        assert_eq!(HTTPRequest::new(b"GET / HTTP/1.1\r\nContent-Length : 5\r\n\r\nhello"), Err(ParseError::new(31, "field-name", Reason::Malformed)));
+        assert_eq!(HTTPRequest::new(b"GET / HTTP/1.1\r\n Host: a\r\n\r\n"), Err(ParseError::new(21, "field-name", Reason::Malformed)));
+        assert_eq!(HTTPRequest::new(b"").unwrap_err().to_string(), "request-line at byte 0: truncated");
+    }
+
+    // A ServerHello record selecting TLS_AES_128_GCM_SHA256 with the random given.
+    fn server_hello(random: [u8; 0x20]) -> Vec<u8> {
+        let mut record = vec![0x16, 0x03, 0x03, 0x00, 0x32, 0x02, 0x00, 0x00, 0x2E, 0x03, 0x03];
+        record.extend_from_slice(&random);
+        record.extend_from_slice(&[0x00, 0x13, 0x01, 0x00, 0x00, 0x06, 0x00, 0x2B, 0x00, 0x02, 0x03, 0x04]);
+        return record;
+    }
+
+    #[test]
+    fn tls_server_hello() {
+        let parsed = Record::new(&server_hello([0x11; 0x20])).unwrap();
+        let handshake = parsed.fragment.unwrap();
+        assert_eq!(handshake.msg_type, HandshakeType::ServerHello);
+        assert_eq!(handshake.msg, Some(HandshakeMessage::ServerHello(ServerHello {
+            legacy_version: 0x0303,
+            random: [0x11; 0x20],
+            legacy_session_id_echo: Vec::new(),
+            cipher_suite: CipherSuite::TLSAES128GCMSHA256,
+            legacy_compression_method: 0,
+            extensions: vec![Extension::SupportedVersions(SupportedVersions::Selected(ProtocolVersion::TLS13))],
+        })));
+    }
+
+    #[test]
+    fn tls_hello_retry_request() {
+        let parsed = Record::new(&server_hello(HELLO_RETRY_REQUEST_RANDOM)).unwrap();
+        match parsed.fragment.unwrap().msg {
+            Some(HandshakeMessage::HelloRetryRequest(hello)) => assert_eq!(hello.cipher_suite, CipherSuite::TLSAES128GCMSHA256),
+            msg => panic!("expected a HelloRetryRequest, found {:?}", msg),
+        };
+    }
+
+    #[test]
+    fn tls_certificate() {
+        // A self-signed P-256 certificate for example.com, followed by one that is not DER.
+        let certificate: Vec<u8> = vec![
+            0x30, 0x82, 0x01, 0xDA, 0x30, 0x82, 0x01, 0x81, 0xA0, 0x03, 0x02, 0x01,
+            0x02, 0x02, 0x02, 0x12, 0x34, 0x30, 0x0A, 0x06, 0x08, 0x2A, 0x86, 0x48,
+            0xCE, 0x3D, 0x04, 0x03, 0x02, 0x30, 0x35, 0x31, 0x0B, 0x30, 0x09, 0x06,
+            0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x10, 0x30, 0x0E,
+            0x06, 0x03, 0x55, 0x04, 0x0A, 0x0C, 0x07, 0x45, 0x78, 0x61, 0x6D, 0x70,
+            0x6C, 0x65, 0x31, 0x14, 0x30, 0x12, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0C,
+            0x0B, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2E, 0x63, 0x6F, 0x6D,
+            0x30, 0x1E, 0x17, 0x0D, 0x32, 0x36, 0x31, 0x30, 0x31, 0x38, 0x30, 0x38,
+            0x31, 0x33, 0x33, 0x38, 0x5A, 0x17, 0x0D, 0x32, 0x37, 0x31, 0x30, 0x31,
+            0x38, 0x30, 0x38, 0x31, 0x33, 0x33, 0x38, 0x5A, 0x30, 0x35, 0x31, 0x0B,
+            0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55, 0x53, 0x31,
+            0x10, 0x30, 0x0E, 0x06, 0x03, 0x55, 0x04, 0x0A, 0x0C, 0x07, 0x45, 0x78,
+            0x61, 0x6D, 0x70, 0x6C, 0x65, 0x31, 0x14, 0x30, 0x12, 0x06, 0x03, 0x55,
+            0x04, 0x03, 0x0C, 0x0B, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2E,
+            0x63, 0x6F, 0x6D, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2A, 0x86, 0x48,
+            0xCE, 0x3D, 0x02, 0x01, 0x06, 0x08, 0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03,
+            0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0x84, 0x09, 0x33, 0xCD, 0x4D, 0xB1,
+            0xE7, 0xB7, 0x69, 0x17, 0xF2, 0xE7, 0x4D, 0x03, 0x98, 0xB6, 0x4B, 0xA4,
+            0xD0, 0x5F, 0x53, 0x1D, 0x0E, 0x38, 0x58, 0x45, 0x1E, 0x7B, 0xEB, 0xF4,
+            0xB5, 0xCE, 0x9D, 0xE3, 0x3F, 0x46, 0x06, 0x45, 0xCF, 0xED, 0x41, 0x7A,
+            0x8E, 0x42, 0xDC, 0xA0, 0xFD, 0x0D, 0x23, 0x44, 0x6C, 0xF5, 0xF8, 0x15,
+            0x3A, 0x7F, 0x37, 0xFA, 0x03, 0x41, 0x76, 0x5C, 0xC5, 0xDB, 0xA3, 0x81,
+            0x80, 0x30, 0x7E, 0x30, 0x1D, 0x06, 0x03, 0x55, 0x1D, 0x0E, 0x04, 0x16,
+            0x04, 0x14, 0x82, 0x74, 0xDA, 0x3F, 0xA2, 0xE8, 0x1D, 0xDF, 0x90, 0xB9,
+            0x47, 0xCF, 0x01, 0xBF, 0x2C, 0xBB, 0x03, 0x45, 0xC4, 0xC6, 0x30, 0x1F,
+            0x06, 0x03, 0x55, 0x1D, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0x82,
+            0x74, 0xDA, 0x3F, 0xA2, 0xE8, 0x1D, 0xDF, 0x90, 0xB9, 0x47, 0xCF, 0x01,
+            0xBF, 0x2C, 0xBB, 0x03, 0x45, 0xC4, 0xC6, 0x30, 0x0F, 0x06, 0x03, 0x55,
+            0x1D, 0x13, 0x01, 0x01, 0xFF, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xFF,
+            0x30, 0x2B, 0x06, 0x03, 0x55, 0x1D, 0x11, 0x04, 0x24, 0x30, 0x22, 0x82,
+            0x0B, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2E, 0x63, 0x6F, 0x6D,
+            0x82, 0x0D, 0x2A, 0x2E, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2E,
+            0x63, 0x6F, 0x6D, 0x87, 0x04, 0x7F, 0x00, 0x00, 0x01, 0x30, 0x0A, 0x06,
+            0x08, 0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02, 0x03, 0x47, 0x00,
+            0x30, 0x44, 0x02, 0x20, 0x79, 0xA0, 0xB0, 0xA9, 0x09, 0x8B, 0xFC, 0x75,
+            0x2B, 0xD2, 0x08, 0x04, 0xB9, 0xE8, 0x6A, 0x8E, 0x69, 0x9A, 0x91, 0x1F,
+            0x94, 0xE4, 0xEB, 0x10, 0xE7, 0x0E, 0x67, 0x38, 0x11, 0x80, 0x7E, 0x26,
+            0x02, 0x20, 0x76, 0x5D, 0xB7, 0xFF, 0x1E, 0x92, 0x88, 0xEA, 0xA1, 0xB8,
+            0x93, 0x0C, 0x9B, 0x9E, 0x01, 0x3F, 0x2D, 0x01, 0xC8, 0xFA, 0x06, 0xAC,
+            0x6B, 0xC3, 0x49, 0xC5, 0x8C, 0x7B, 0xB7, 0x70, 0xBA, 0x60];
+        let mut list = vec![0x00, 0x01, 0xDE];
+        list.extend_from_slice(&certificate);
+        list.extend_from_slice(&[0x00, 0x00, 0x02, 0x30, 0x05]);
+        let mut record = vec![0x16, 0x03, 0x03, 0x01, 0xED, 0x0B, 0x00, 0x01, 0xE9, 0x00, 0x01, 0xE6];
+        record.extend_from_slice(&list);
+
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.to_bytes().unwrap(), record);
+        let chain = match parsed.fragment.unwrap().msg {
+            Some(HandshakeMessage::Certificate(chain)) => chain,
+            msg => panic!("expected a Certificate, found {:?}", msg),
+        };
+        assert_eq!(chain, Certificate { certificate_list: vec![certificate, vec![0x30, 0x05]] });
+        let x509 = chain.x509();
+        let first = x509[0].as_ref().unwrap();
+        assert_eq!(first.version, 3);
+        assert_eq!(first.serial_number, vec![0x12, 0x34]);
+        assert_eq!(first.subject, "C=US, O=Example, CN=example.com");
+        assert_eq!(first.issuer, first.subject);
+        assert_eq!(first.subject_alt_names, vec!["example.com", "*.example.com", "127.0.0.1"]);
+        assert_eq!(first.not_before, "2026-10-18 08:13:38 UTC");
+        assert_eq!(first.not_after, "2027-10-18 08:13:38 UTC");
+        assert_eq!(first.public_key, PublicKey::EC(String::from("P-256")));
+        assert_eq!(first.signature_algorithm, "ecdsa-with-SHA256");
+        assert_eq!(x509[1], Err(ParseError::new(1, "certificate", Reason::InvalidLength)));
+    }
+
+    #[test]
+    fn tls12_key_exchange() {
+        // A server flight in one record: ECDHE parameters signed as in TLS 1.2, then
+        // ServerHelloDone.
+        let mut server = vec![0x16, 0x03, 0x03, 0x00, 0x38, 0x0C, 0x00, 0x00, 0x30, 0x03, 0x00, 0x1D, 0x20];
+        server.extend_from_slice(&[0xAA; 0x20]);
+        server.extend_from_slice(&[0x08, 0x04, 0x00, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+        server.extend_from_slice(&[0x0E, 0x00, 0x00, 0x00]);
+        let records: Vec<_> = RecordReader::new(&server).map(|record| record.unwrap()).collect();
+        let messages: Vec<_> = records[0].messages.iter().map(|message| match message {
+            Message::Handshake(handshake) => handshake,
+            _ => panic!("expected handshake messages"),
+        }).collect();
+        assert_eq!(messages[0].msg, Some(HandshakeMessage::ServerKeyExchange(ServerKeyExchange::ECDHE {
+            named_curve: NamedGroup::X25519,
+            public: vec![0xAA; 0x20],
+            signature: DigitallySigned { algorithm: Some(SignatureScheme::RSAPSSRSAESHA256), signature: vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08] },
+        })));
+        assert_eq!(messages[1].msg_type, HandshakeType::ServerHelloDone);
+        assert_eq!(messages[1].msg, Some(HandshakeMessage::ServerHelloDone));
+        assert_eq!(messages[0].to_bytes().unwrap(), server[5..0x39]);
+        assert_eq!(messages[1].to_bytes().unwrap(), server[0x39..]);
+
+        // DHE parameters signed as in TLS 1.0 and 1.1, without the algorithm.
+        let record = vec![0x16, 0x03, 0x01, 0x00, 0x12, 0x0C, 0x00, 0x00, 0x0E,
+            0x00, 0x02, 0xFF, 0xFB, 0x00, 0x01, 0x02, 0x00, 0x02, 0x12, 0x34, 0x00, 0x01, 0x99];
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.to_bytes().unwrap(), record);
+        assert_eq!(parsed.fragment.unwrap().msg, Some(HandshakeMessage::ServerKeyExchange(ServerKeyExchange::DHE {
+            p: vec![0xFF, 0xFB],
+            g: vec![0x02],
+            public: vec![0x12, 0x34],
+            signature: DigitallySigned { algorithm: None, signature: vec![0x99] },
+        })));
+
+        // Parameters of other key exchanges, such as a PSK identity hint, are left unparsed.
+        let record = vec![0x16, 0x03, 0x03, 0x00, 0x07, 0x0C, 0x00, 0x00, 0x03, 0x00, 0x05, 0x68];
+        let handshake = Record::new(&record).unwrap().fragment.unwrap();
+        assert_eq!((handshake.msg_type, handshake.msg), (HandshakeType::ServerKeyExchange, None));
+
+        // Clients send an ECDH public key with a 1-byte length, and RSA and DH values with two.
+        let record = vec![0x16, 0x03, 0x03, 0x00, 0x08, 0x10, 0x00, 0x00, 0x04, 0x03, 0x04, 0x05, 0x06];
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.to_bytes().unwrap(), record);
+        assert_eq!(parsed.fragment.unwrap().msg, Some(HandshakeMessage::ClientKeyExchange(ClientKeyExchange::ECDHE(vec![0x04, 0x05, 0x06]))));
+        let record = vec![0x16, 0x03, 0x03, 0x00, 0x08, 0x10, 0x00, 0x00, 0x04, 0x00, 0x02, 0xAB, 0xCD];
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.fragment.unwrap().msg, Some(HandshakeMessage::ClientKeyExchange(ClientKeyExchange::RSAOrDHE(vec![0xAB, 0xCD]))));
+    }
+
+    #[test]
+    fn tls_alerts() {
+        // A server refusing a ClientHello.
+        let record = [0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.alert, Some(Alert { level: AlertLevel::Fatal, description: AlertDescription::HandshakeFailure }));
+        assert_eq!(parsed.properties().last(), Some(&(String::from("alert"), String::from("fatal: handshake_failure"))));
+        assert_eq!(parsed.to_bytes().unwrap(), record);
+
+        // Alerts in the clear, then an encrypted one following a change of cipher spec.
+        let mut capture = vec![0x15, 0x03, 0x03, 0x00, 0x04, 0x01, 0x70, 0x02, 0x30];
+        capture.extend_from_slice(&[0x14, 0x03, 0x03, 0x00, 0x01, 0x01]);
+        capture.extend_from_slice(&[0x15, 0x03, 0x03, 0x00, 0x03, 0xA1, 0xA2, 0xA3]);
+        let records: Vec<_> = RecordReader::new(&capture).map(|record| record.unwrap()).collect();
+        assert_eq!(records[0].messages, vec![
+            Message::Alert(Alert { level: AlertLevel::Warning, description: AlertDescription::UnrecognizedName }),
+            Message::Alert(Alert { level: AlertLevel::Fatal, description: AlertDescription::UnknownCA }),
+        ]);
+        assert_eq!(records[2].messages, vec![Message::EncryptedAlert(vec![0xA1, 0xA2, 0xA3])]);
+
+        // A TLS 1.2 close_notify encrypted with AES-GCM: an explicit nonce, the alert and a tag.
+        let mut record = vec![0x15, 0x03, 0x03, 0x00, 0x1A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        record.extend_from_slice(&[0x9C; 0x12]);
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.alert, None);
+        assert_eq!(parsed.properties().last(), Some(&(String::from("encrypted alert"), String::from("26 bytes"))));
+        assert_eq!(Alert::from_capture(&record), None);
+
+        // An alert missing its description.
+        let capture = [0x15, 0x03, 0x03, 0x00, 0x01, 0x02];
+        assert_eq!(RecordReader::new(&capture).next(), Some(Err(ParseError::new(6, "description", Reason::Truncated))));
+
+        // Flows show the alerts starting their request or response.
+        let mut flow = Flow::new(1, 1, "127.0.0.1:50000".parse().unwrap(), Request::new(100, vec![0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x30]));
+        flow.extend_response(120, &[0x15, 0x03, 0x03, 0x00]);
+        assert_eq!(flow.server_alert, None);
+        flow.extend_response(130, &[0x02, 0x02, 0x46]);
+        assert_eq!(flow.to_string(), "#1 connection 1 127.0.0.1:50000 -> - alert=fatal: unknown_ca (client) alert=fatal: protocol_version (server)");
+    }
+
+    #[test]
+    fn tls_record_sequence() {
+        let mut capture = server_hello([0x11; 0x20]);
+        capture.extend_from_slice(&[0x14, 0x03, 0x03, 0x00, 0x01, 0x01]);
+        capture.extend_from_slice(&[0x16, 0x03, 0x03, 0x00, 0x02, 0xAA, 0xBB]);
+        capture.extend_from_slice(&[0x17, 0x03, 0x03, 0x00, 0x03, 0x01, 0x02, 0x03]);
+        let records: Vec<_> = RecordReader::new(&capture).map(|record| record.unwrap()).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].r#type, RecordContentType::Handshake);
+        match records[0].messages[..] {
+            [Message::Handshake(ref handshake)] => assert_eq!(handshake.msg_type, HandshakeType::ServerHello),
+            _ => panic!("expected a ServerHello"),
+        };
+        assert_eq!(records[1].offset, 0x37);
+        assert_eq!(records[1].messages, vec![Message::ChangeCipherSpec]);
+        assert_eq!(records[2].messages, vec![Message::EncryptedHandshake(vec![0xAA, 0xBB])]);
+        assert_eq!(records[3].messages, vec![Message::ApplicationData(vec![0x01, 0x02, 0x03])]);
+    }
+
+    #[test]
+    fn tls_fragmented_handshake() {
+        // The ServerHello split across two records, followed by a second message in the last one.
+        let hello = server_hello([0x11; 0x20]);
+        let message = &hello[5..];
+        let mut capture = vec![0x16, 0x03, 0x03, 0x00, 0x10];
+        capture.extend_from_slice(&message[..0x10]);
+        capture.extend_from_slice(&[0x16, 0x03, 0x03, 0x00, (message.len() - 0x10 + 4) as u8]);
+        capture.extend_from_slice(&message[0x10..]);
+        capture.extend_from_slice(&[0x08, 0x00, 0x00, 0x00]);
+        let records: Vec<_> = RecordReader::new(&capture).map(|record| record.unwrap()).collect();
+        assert_eq!(records[0].messages, Vec::new());
+        match records[1].messages[..] {
+            [Message::Handshake(ref hello), Message::Handshake(ref extensions)] => {
+                assert_eq!(hello.msg_type, HandshakeType::ServerHello);
+                assert_eq!(extensions.msg_type, HandshakeType::EncryptedExtensions);
+            },
+            _ => panic!("expected a ServerHello and EncryptedExtensions"),
+        };
+
+        // Errors in a reassembled message are reported at their offset in the capture. The
+        // extensions length in the second record is made longer than the message.
+        let mut capture = vec![0x16, 0x03, 0x03, 0x00, 0x10];
+        capture.extend_from_slice(&message[..0x10]);
+        capture.extend_from_slice(&[0x16, 0x03, 0x03, 0x00, (message.len() - 0x10) as u8]);
+        capture.extend_from_slice(&message[0x10..]);
+        capture[0x34] = 0x00;
+        capture[0x35] = 0x20;
+        let error = RecordReader::new(&capture).find_map(|record| record.err()).unwrap();
+        assert_eq!(error, ParseError::new(0x34, "extensions", Reason::InvalidLength));
+
+        // A message missing the rest of its bytes at the end of the capture.
+        let mut capture = vec![0x16, 0x03, 0x03, 0x00, 0x10];
+        capture.extend_from_slice(&message[..0x10]);
+        let mut records = RecordReader::new(&capture);
+        assert!(records.next().unwrap().is_ok());
+        assert_eq!(records.next(), Some(Err(ParseError::new(5, "handshake", Reason::Truncated))));
+        assert_eq!(records.next(), None);
+    }
+
+    #[test]
+    fn tls_extension_payloads() {
+        // A ClientHello with the given extensions and no cipher suites.
+        fn client_hello(extensions: &[u8]) -> Vec<u8> {
+            let mut hello = vec![0x03, 0x03];
+            hello.extend_from_slice(&[0x00; 0x20]);
+            hello.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00]);
+            hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+            hello.extend_from_slice(extensions);
+            let mut record = vec![0x16, 0x03, 0x01];
+            record.extend_from_slice(&(hello.len() as u16 + 4).to_be_bytes());
+            record.extend_from_slice(&[0x01, 0x00]);
+            record.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+            record.extend_from_slice(&hello);
+            return record;
+        }
+
+        let record = client_hello(&[
+            0x00, 0x01, 0x00, 0x01, 0x02,
+            0x00, 0x05, 0x00, 0x05, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x2C, 0x00, 0x04, 0x00, 0x02, 0xAB, 0xCD,
+            0x00, 0x29, 0x00, 0x11, 0x00, 0x08, 0x00, 0x02, 0x01, 0x02, 0x00, 0x00, 0x00, 0x07,
+            0x00, 0x05, 0x04, 0xB0, 0xB1, 0xB2, 0xB3]);
+        let msg = Record::new(&record).unwrap().fragment.unwrap().msg.unwrap();
+        assert_eq!(msg.extensions(), vec![
+            Extension::MaxFragmentLength(MaxFragmentLength::Bytes1024),
+            Extension::StatusRequest(Some(CertificateStatusRequest { status_type: 1, responder_id_list: Vec::new(), request_extensions: Vec::new() })),
+            Extension::Cookie(vec![0xAB, 0xCD]),
+            Extension::PreSharedKey(PreSharedKey::Offered {
+                identities: vec![PSKIdentity { identity: vec![0x01, 0x02], obfuscated_ticket_age: 7 }],
+                binders: vec![vec![0xB0, 0xB1, 0xB2, 0xB3]],
+            }),
+        ]);
+
+        // Servers send a single key share, or only the selected group when retrying.
+        let mut record = server_hello([0x11; 0x20]);
+        record.truncate(record.len() - 8);
+        record.extend_from_slice(&[0x00, 0x0A, 0x00, 0x33, 0x00, 0x06, 0x00, 0x1D, 0x00, 0x02, 0xEE, 0xFF]);
+        record[4] += 4;
+        record[8] += 4;
+        let msg = Record::new(&record).unwrap().fragment.unwrap().msg.unwrap();
+        assert_eq!(msg.extensions(), vec![Extension::KeyShare(KeyShare::ServerShare(KeyShareEntry { group: NamedGroup::X25519, key_exchange: vec![0xEE, 0xFF] }))]);
+
+        let mut record = server_hello(HELLO_RETRY_REQUEST_RANDOM);
+        record.truncate(record.len() - 8);
+        record.extend_from_slice(&[0x00, 0x06, 0x00, 0x33, 0x00, 0x02, 0x00, 0x1D]);
+        let msg = Record::new(&record).unwrap().fragment.unwrap().msg.unwrap();
+        assert_eq!(msg.extensions(), vec![Extension::KeyShare(KeyShare::SelectedGroup(NamedGroup::X25519))]);
+
+        // Two byte extension data is decoded by the message carrying it, not by its length. An
+        // empty list of key shares asks the server to select a group.
+        let record = client_hello(&[
+            0x00, 0x33, 0x00, 0x02, 0x00, 0x00,
+            0x00, 0x29, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x2B, 0x00, 0x01, 0x00]);
+        let msg = Record::new(&record).unwrap().fragment.unwrap().msg.unwrap();
+        assert_eq!(msg.extensions(), vec![
+            Extension::KeyShare(KeyShare::ClientShares(Vec::new())),
+            Extension::PreSharedKey(PreSharedKey::Offered { identities: Vec::new(), binders: Vec::new() }),
+            Extension::SupportedVersions(SupportedVersions::Offered(Vec::new())),
+        ]);
+
+        // Extension data that would not be written back as sent once decoded is kept as sent: a
+        // server name of an unknown type, a protocol name that is not UTF-8, padding that is not
+        // zero and a payload where none is defined.
+        let record = client_hello(&[
+            0x00, 0x00, 0x00, 0x0A, 0x00, 0x08, 0x00, 0x00, 0x01, 0x61, 0x01, 0x00, 0x01, 0x62,
+            0x00, 0x10, 0x00, 0x04, 0x00, 0x02, 0x01, 0xFF,
+            0x00, 0x15, 0x00, 0x02, 0x00, 0x01,
+            0x00, 0x17, 0x00, 0x01, 0x00]);
+        let parsed = Record::new(&record).unwrap();
+        assert_eq!(parsed.fragment.as_ref().unwrap().msg.as_ref().unwrap().extensions(), vec![
+            Extension::Undecoded { id: 0x0000, data: vec![0x00, 0x08, 0x00, 0x00, 0x01, 0x61, 0x01, 0x00, 0x01, 0x62] },
+            Extension::Undecoded { id: 0x0010, data: vec![0x00, 0x02, 0x01, 0xFF] },
+            Extension::Undecoded { id: 0x0015, data: vec![0x00, 0x01] },
+            Extension::Undecoded { id: 0x0017, data: vec![0x00] },
+        ]);
+        assert_eq!(parsed.to_bytes().unwrap(), record);
+    }
+
+    #[test]
+    fn tls_grease_and_unknown_extensions() {
+        let mut record = vec![0x16, 0x03, 0x01, 0x00, 0x45, 0x01, 0x00, 0x00, 0x41, 0x03, 0x03];
+        record.extend_from_slice(&[0x00; 0x20]);
+        record.extend_from_slice(&[0x00, 0x00, 0x04, 0x1A, 0x1A, 0x13, 0x01, 0x01, 0x00, 0x00, 0x14]);
+        record.extend_from_slice(&[
+            0x2A, 0x2A, 0x00, 0x01, 0x00,
+            0x00, 0x2B, 0x00, 0x05, 0x04, 0x4A, 0x4A, 0x03, 0x04,
+            0xFE, 0x0D, 0x00, 0x02, 0xAB, 0xCD]);
+        let msg = Record::new(&record).unwrap().fragment.unwrap().msg.unwrap();
+        match msg {
+            HandshakeMessage::ClientHello(ref hello) => assert_eq!(hello.cipher_suites, vec![CipherSuite::GREASE(0x1A, 0x1A), CipherSuite::TLSAES128GCMSHA256]),
+            _ => panic!("expected a ClientHello"),
+        };
+        assert_eq!(msg.extensions(), vec![
+            Extension::Grease { id: 0x2A2A, data: vec![0x00] },
+            Extension::SupportedVersions(SupportedVersions::Offered(vec![ProtocolVersion::Grease(0x4A4A), ProtocolVersion::TLS13])),
+            Extension::Unknown { id: 0xFE0D, data: vec![0xAB, 0xCD] },
+        ]);
+    }
+
+    #[test]
+    fn tls_fingerprints() {
+        // A ClientHello with GREASE values among its cipher suites, extensions, groups and
+        // versions.
+        let hello = vec![0x16, 0x03, 0x01, 0x00, 0x7D, 0x01, 0x00, 0x00,
+            0x79, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x0A, 0x0A,
+            0x13, 0x01, 0x13, 0x02, 0xC0, 0x2B, 0x01, 0x00,
+            0x00, 0x48, 0x1A, 0x1A, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x0E, 0x00, 0x00, 0x0B, 0x65,
+            0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2E, 0x63,
+            0x6F, 0x6D, 0x00, 0x10, 0x00, 0x05, 0x00, 0x03,
+            0x02, 0x68, 0x32, 0x00, 0x0A, 0x00, 0x08, 0x00,
+            0x06, 0x2A, 0x2A, 0x00, 0x1D, 0x00, 0x17, 0x00,
+            0x0B, 0x00, 0x02, 0x01, 0x00, 0x00, 0x0D, 0x00,
+            0x06, 0x00, 0x04, 0x04, 0x03, 0x08, 0x04, 0x00,
+            0x2B, 0x00, 0x07, 0x06, 0x3A, 0x3A, 0x03, 0x04,
+            0x03, 0x03];
+        let fingerprint = Fingerprint::from_capture(&hello).unwrap();
+        assert_eq!(fingerprint.ja3_string, "771,4865-4866-49195,0-16-10-11-13-43,29-23,0");
+        assert_eq!(fingerprint.ja3, "3736761f91e3f9597a641ce4c92f256c");
+        assert_eq!(fingerprint.ja4, "t13d0306h2_5559582ccdc4_fb71836bce29");
+
+        let flow = Flow::new(1, 1, "127.0.0.1:50000".parse().unwrap(), Request::new(100, hello));
+        assert_eq!(flow.fingerprint, Some(fingerprint));
+        assert!(flow.to_string().ends_with(" ja4=t13d0306h2_5559582ccdc4_fb71836bce29 ja3=3736761f91e3f9597a641ce4c92f256c"));
+        assert_eq!(Fingerprint::from_capture(b"GET / HTTP/1.1\r\n\r\n"), None);
     }
 }
@@ -0,0 +1,157 @@
+// Parsers are given arbitrary and mutated captures, as a hostile peer could send, and must return
+// an error rather than panic. Run with `cargo test -p parse --test property_tests`; set
+// PROPTEST_CASES to run more cases than the default 256.
+mod tests {
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use parse::Request;
+    use parse::http_request::HTTPRequest;
+    use parse::tls_request::{Record, RecordReader, ClientHelloBuilder, HandshakeMessage};
+    use parse::tls_cipher_suite::CipherSuite;
+    use parse::tls_extension::{Extension, ExtensionContext, NamedGroup};
+    use parse::x509;
+    use tui::draw::LogEntry;
+    use tui::Encoding;
+
+    // A ClientHello record with server name, ALPN, supported groups, signature algorithms,
+    // supported versions, key share and PSK extensions, for mutating.
+    fn client_hello() -> Vec<u8> {
+        let extensions: &[u8] = &[
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x0E, 0x00, 0x00, 0x0B, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2E, 0x63, 0x6F, 0x6D,
+            0x00, 0x10, 0x00, 0x0E, 0x00, 0x0C, 0x02, 0x68, 0x32, 0x08, 0x68, 0x74, 0x74, 0x70, 0x2F, 0x31, 0x2E, 0x31,
+            0x00, 0x0A, 0x00, 0x06, 0x00, 0x04, 0x00, 0x1D, 0x00, 0x17,
+            0x00, 0x0D, 0x00, 0x06, 0x00, 0x04, 0x04, 0x03, 0x08, 0x04,
+            0x00, 0x2B, 0x00, 0x05, 0x04, 0x03, 0x04, 0x03, 0x03,
+            0x00, 0x33, 0x00, 0x0A, 0x00, 0x08, 0x00, 0x1D, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04,
+            0x00, 0x29, 0x00, 0x11, 0x00, 0x08, 0x00, 0x02, 0x01, 0x02, 0x00, 0x00, 0x00, 0x07, 0x00, 0x05, 0x04, 0xB0, 0xB1, 0xB2, 0xB3];
+        let mut hello = vec![0x03, 0x03];
+        hello.extend_from_slice(&[0x00; 0x20]);
+        hello.extend_from_slice(&[0x00, 0x00, 0x04, 0x13, 0x01, 0x13, 0x02, 0x01, 0x00]);
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(extensions);
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend_from_slice(&(hello.len() as u16 + 4).to_be_bytes());
+        record.extend_from_slice(&[0x01, 0x00]);
+        record.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+        record.extend_from_slice(&hello);
+        return record;
+    }
+
+    // Parse the capture the ways the proxy and the TUI do.
+    fn parse_tls(data: &[u8]) {
+        let _ = Record::new(data);
+        for _ in RecordReader::new(data) {}
+        Request::new(0, data.to_vec()).to_lines(&Encoding::Protocol(String::from("tls")));
+    }
+
+    fn parse_http(data: &[u8]) {
+        let _ = HTTPRequest::new(data);
+        Request::new(0, data.to_vec()).to_lines(&Encoding::Protocol(String::from("http")));
+    }
+
+    #[test]
+    fn client_hello_parses() {
+        let hello = client_hello();
+        assert!(Record::new(&hello).is_ok());
+        assert!(RecordReader::new(&hello).all(|record| record.is_ok()));
+        assert_eq!(Record::new(&hello).unwrap().to_bytes().unwrap(), hello);
+    }
+
+    proptest! {
+        #[test]
+        fn tls_arbitrary_bytes(data in vec(any::<u8>(), 0..512)) {
+            parse_tls(&data);
+        }
+
+        #[test]
+        fn tls_mutated_client_hello(changes in vec((any::<usize>(), any::<u8>()), 1..8), length in any::<usize>()) {
+            let mut data = client_hello();
+            for (i, byte) in changes {
+                let i = i % data.len();
+                data[i] = byte;
+            }
+            data.truncate(length % (data.len() + 1));
+            parse_tls(&data);
+        }
+
+        #[test]
+        fn tls_split_client_hello(split in 1usize..0x90) {
+            // The handshake message spread across two records, cut at an arbitrary point.
+            let hello = client_hello();
+            let message = &hello[5..];
+            let split = split.min(message.len() - 1);
+            let mut data = Vec::new();
+            for fragment in [&message[..split], &message[split..]] {
+                data.extend_from_slice(&[0x16, 0x03, 0x03]);
+                data.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+                data.extend_from_slice(fragment);
+            }
+            prop_assert!(RecordReader::new(&data).all(|record| record.is_ok()));
+        }
+
+        #[test]
+        fn x509_arbitrary_bytes(data in vec(any::<u8>(), 0..512)) {
+            let _ = x509::Certificate::new(&data);
+            // Nested sequences, to get past the outer certificate and tbsCertificate elements.
+            let _ = x509::Certificate::new(&[&[0x30, 0x81, 0xFF, 0x30, 0x81, 0xFC][..], &data].concat());
+        }
+
+        #[test]
+        fn tls_extension_data_round_trip(extension_type in 0u16..64, data in vec(any::<u8>(), 0..64)) {
+            let mut expected = extension_type.to_be_bytes().to_vec();
+            expected.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            expected.extend_from_slice(&data);
+            for context in [ExtensionContext::ClientHello, ExtensionContext::ServerHello, ExtensionContext::HelloRetryRequest] {
+                prop_assert_eq!(Extension::new(extension_type, &data, context).to_bytes(), Some(expected.clone()));
+            }
+        }
+
+        #[test]
+        fn tls_built_client_hello_round_trip(
+            random in any::<[u8; 0x20]>(),
+            cipher_suites in vec(any::<(u8, u8)>(), 0..16),
+            server_name in "[a-z0-9.-]{1,32}",
+            protocols in vec("[ -~]{1,16}", 1..4),
+            key_shares in vec((any::<u16>(), vec(any::<u8>(), 1..64)), 0..3),
+        ) {
+            let mut builder = ClientHelloBuilder::new()
+                .random(random)
+                .cipher_suites(cipher_suites.iter().map(|(b0, b1)| CipherSuite::new(*b0, *b1)).collect())
+                .server_name(&server_name)
+                .alpn(&protocols.iter().map(|protocol| protocol.as_str()).collect::<Vec<_>>());
+            for (group, key_exchange) in key_shares {
+                builder = builder.key_share(NamedGroup::new(group), key_exchange);
+            }
+            let hello = builder.build();
+            let record = hello.to_record().unwrap();
+            let parsed = Record::new(&record).unwrap();
+            prop_assert_eq!(parsed.to_bytes().unwrap(), record);
+            prop_assert_eq!(parsed.fragment.unwrap().msg, Some(HandshakeMessage::ClientHello(hello)));
+        }
+
+        #[test]
+        fn http_arbitrary_bytes(data in vec(any::<u8>(), 0..512)) {
+            parse_http(&data);
+            // Captures starting with the version are parsed as responses.
+            parse_http(&[b"HTTP/1.1 ", &data[..]].concat());
+        }
+
+        #[test]
+        fn http_request_round_trip(
+            method in "[A-Z]{1,7}",
+            target in "/[a-z0-9/?=&]{0,16}",
+            headers in vec(("[A-Za-z-]{1,12}", "[!-~]([ -~]{0,14}[!-~])?"), 0..6),
+            body in vec(any::<u8>(), 0..64),
+        ) {
+            let mut data = format!("{} {} HTTP/1.1\r\n", method, target);
+            for (name, value) in headers.iter() {
+                data.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            data.push_str("\r\n");
+            let mut data = data.into_bytes();
+            data.extend_from_slice(&body);
+            let request = HTTPRequest::new(&data).unwrap();
+            prop_assert_eq!(request.to_bytes(), data);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use parse::flow::Flow;
+
+use crate::ca::CertificateAuthority;
+use crate::event::{ProxyError, ProxyEvent};
+use crate::hold::{HoldRules, Holds, Verdict};
+use crate::repeater;
+use crate::{listen, Config, Counters, Limits, Reporter, Slots};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+// Configures a proxy before it starts listening.
+pub struct ProxyBuilder {
+    addresses: Vec<String>,
+    authority: Option<CertificateAuthority>,
+    limits: Limits,
+    upstream_proxy: Option<String>,
+    hold_rules: HoldRules,
+}
+
+impl Default for ProxyBuilder {
+    fn default() -> Self {
+        ProxyBuilder::new()
+    }
+}
+
+impl ProxyBuilder {
+    pub fn new() -> Self {
+        ProxyBuilder {
+            addresses: Vec::new(),
+            authority: None,
+            limits: Limits::default(),
+            upstream_proxy: None,
+            hold_rules: HoldRules::default(),
+        }
+    }
+
+    // Listen on the address, such as 127.0.0.1:7878 or [::1]:8080. Called once for every address
+    // to listen on. Port 0 listens on a port chosen by the system. Defaults to 127.0.0.1:7878.
+    pub fn listen(mut self, address: &str) -> Self {
+        self.addresses.push(address.to_string());
+        self
+    }
+
+    // Terminate TLS in CONNECT tunnels using certificates issued by the authority.
+    pub fn intercept(mut self, authority: CertificateAuthority) -> Self {
+        self.authority = Some(authority);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.limits.max_connections = max_connections;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.timeout = timeout;
+        self
+    }
+
+    // Reach origin servers through a tunnel opened by another HTTP proxy at the address.
+    pub fn upstream_proxy(mut self, address: &str) -> Self {
+        self.upstream_proxy = Some(address.to_string());
+        self
+    }
+
+    // Hold the messages selected by the rules until a verdict is given. Nothing is held by default.
+    pub fn hold(mut self, rules: HoldRules) -> Self {
+        self.hold_rules = rules;
+        self
+    }
+
+    // Bind every address and serve clients in the background, reporting events to the sender.
+    // Nothing is served if any address cannot be bound.
+    pub fn start(self, sender: mpsc::Sender<ProxyEvent>) -> Result<ProxyHandle, ProxyError> {
+        let addresses = if self.addresses.is_empty() { vec![String::from(DEFAULT_ADDRESS)] } else { self.addresses };
+        let mut proxy = ProxyHandle {
+            addresses: addresses,
+            slots: Arc::new(Slots::new(self.limits.max_connections)),
+            config: Arc::new(Config {
+                authority: self.authority,
+                limits: self.limits,
+                upstream_proxy: self.upstream_proxy,
+                holds: Holds::new(self.hold_rules),
+            }),
+            reporter: Reporter {
+                sender: sender,
+                counters: Arc::new(Counters::default()),
+            },
+            listeners: Vec::new(),
+        };
+        proxy.restart()?;
+        return Ok(proxy);
+    }
+}
+
+// Counts of what a proxy has served since it was built.
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct Stats {
+    pub running: bool,
+    pub connections: u64,
+    pub active_connections: usize,
+    pub flows: u64,
+    pub errors: u64,
+}
+
+// A running proxy. Dropping the handle stops it.
+pub struct ProxyHandle {
+    addresses: Vec<String>,
+    config: Arc<Config>,
+    reporter: Reporter,
+    // Shared by all listeners so the connection limit applies to the proxy as a whole.
+    slots: Arc<Slots>,
+    listeners: Vec<Listener>,
+}
+
+struct Listener {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl ProxyHandle {
+    // Addresses being listened on, with the ports chosen by the system in place of port 0.
+    pub fn local_addresses(&self) -> Vec<SocketAddr> {
+        self.listeners.iter().map(|listener| listener.address).collect()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.listeners.is_empty()
+    }
+
+    pub fn stats(&self) -> Stats {
+        let counters = &self.reporter.counters;
+        Stats {
+            running: self.is_running(),
+            connections: counters.connections.load(Ordering::Relaxed),
+            active_connections: self.slots.used(),
+            flows: counters.flows.load(Ordering::Relaxed),
+            errors: counters.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn hold_rules(&self) -> HoldRules {
+        self.config.holds.rules()
+    }
+
+    // Change which messages are held. Turning holding off forwards every message already held.
+    pub fn set_hold_rules(&self, rules: HoldRules) {
+        self.config.holds.set_rules(rules);
+    }
+
+    // Give the verdict for a held message. Returns false if no message is held with the ID.
+    pub fn release(&self, id: u64, verdict: Verdict) -> bool {
+        self.config.holds.resolve(id, verdict)
+    }
+
+    // Send the request to the origin server the flow's request was relayed to, reporting the
+    // attempt once answered. Flows that were not relayed as HTTP requests cannot be repeated.
+    pub fn repeat(&self, flow: &Flow, request: Vec<u8>) -> Result<(), ProxyError> {
+        let origin = match flow.origin {
+            Some(ref origin) => origin.clone(),
+            None => return Err(ProxyError::Repeat { flow: flow.id, reason: String::from("no HTTP request was relayed") }),
+        };
+        let flow = flow.clone();
+        let config = self.config.clone();
+        let reporter = self.reporter.clone();
+        thread::spawn(move || repeater::repeat(&flow, &origin, request, &config, &reporter));
+        return Ok(());
+    }
+
+    // Stop accepting clients. Connections already accepted are served until they close.
+    pub fn stop(&mut self) {
+        // Wake listeners waiting for a free connection slot. The slot count is locked so that no
+        // listener can find it is not stopped and then wait after the notification was sent.
+        {
+            let _used = self.slots.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for listener in self.listeners.iter() {
+                listener.stopped.store(true, Ordering::SeqCst);
+            }
+            self.slots.released.notify_all();
+        }
+        // Wake listeners waiting for a client to connect.
+        for listener in self.listeners.drain(..) {
+            let _ = TcpStream::connect(reachable(listener.address));
+            let _ = listener.thread.join();
+        }
+    }
+
+    // Listen on the configured addresses again, stopping first if running.
+    pub fn restart(&mut self) -> Result<(), ProxyError> {
+        self.stop();
+
+        let mut listeners = Vec::new();
+        for address in self.addresses.iter() {
+            match TcpListener::bind(address.as_str()) {
+                Ok(listener) => listeners.push((address.clone(), listener)),
+                Err(error) => return Err(ProxyError::Bind { address: address.clone(), error: error }),
+            };
+        }
+        for (configured, listener) in listeners {
+            let address = match listener.local_addr() {
+                Ok(address) => address,
+                Err(error) => {
+                    self.stop();
+                    return Err(ProxyError::Bind { address: configured, error: error });
+                },
+            };
+            let stopped = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let stopped = stopped.clone();
+                let slots = self.slots.clone();
+                let config = self.config.clone();
+                let reporter = self.reporter.clone();
+                thread::spawn(move || listen(listener, stopped, slots, config, reporter))
+            };
+            self.listeners.push(Listener { address: address, stopped: stopped, thread: thread });
+        }
+        return Ok(());
+    }
+
+    // Block until every listener has exited.
+    pub fn wait(mut self) {
+        for listener in std::mem::take(&mut self.listeners) {
+            let _ = listener.thread.join();
+        }
+    }
+}
+
+impl Drop for ProxyHandle {
+    fn drop(&mut self) {
+        self.stop();
+        self.config.holds.release_all();
+    }
+}
+
+// Listeners bound to the unspecified address are reached through the loopback address.
+fn reachable(address: SocketAddr) -> SocketAddr {
+    match address.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), address.port()),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), address.port()),
+        _ => address,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn stop_with_every_slot_used() {
+        let (sender, _receiver) = mpsc::channel();
+        let mut proxy = ProxyBuilder::new().listen("127.0.0.1:0").max_connections(2).timeout(None).start(sender).unwrap();
+        let address = proxy.local_addresses()[0];
+        // Clients that connect and send nothing keep their slots.
+        let _clients: Vec<TcpStream> = (0..2).map(|_| TcpStream::connect(address).unwrap()).collect();
+        for _ in 0..100 {
+            if proxy.stats().active_connections == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(proxy.stats().active_connections, 2);
+
+        let (stopped, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            proxy.stop();
+            let _ = stopped.send(proxy.is_running());
+        });
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(false));
+    }
+}
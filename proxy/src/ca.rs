@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use rcgen::{BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, SerialNumber};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::ServerConfig;
+use time::{Duration, OffsetDateTime};
+
+const CERTIFICATE_FILE: &str = "ca.pem";
+const KEY_FILE: &str = "ca.key";
+
+// A root certificate authority issuing leaf certificates for intercepted hosts. Clients must trust
+// the root certificate, written to ca.pem in the authority's directory, for interception to work.
+pub struct CertificateAuthority {
+    key: KeyPair,
+    certificate: rcgen::Certificate,
+    cache: Mutex<HashMap<String, Arc<ServerConfig>>>,
+}
+
+impl CertificateAuthority {
+    // Load the root key from the directory, generating and saving a new one if none exists.
+    pub fn load_or_generate(directory: &Path) -> io::Result<Self> {
+        let key_path = directory.join(KEY_FILE);
+        let key = if key_path.exists() {
+            KeyPair::from_pem(&fs::read_to_string(&key_path)?).map_err(io::Error::other)?
+        } else {
+            fs::create_dir_all(directory)?;
+            let key = KeyPair::generate().map_err(io::Error::other)?;
+            write_private(&key_path, key.serialize_pem().as_bytes())?;
+            key
+        };
+
+        // The root certificate is rebuilt from fixed parameters so that it is identical, other than
+        // the signature, across runs using the same key.
+        let certificate = Self::root_parameters().self_signed(&key).map_err(io::Error::other)?;
+        let certificate_path = directory.join(CERTIFICATE_FILE);
+        if !certificate_path.exists() {
+            fs::write(&certificate_path, certificate.pem())?;
+        }
+
+        return Ok(CertificateAuthority {
+            key: key,
+            certificate: certificate,
+            cache: Mutex::new(HashMap::new()),
+        });
+    }
+
+    pub fn certificate_pem(&self) -> String {
+        self.certificate.pem()
+    }
+
+    // TLS server configuration presenting a leaf certificate for the host name, issued on first use.
+    pub fn server_config(&self, host_name: &str) -> io::Result<Arc<ServerConfig>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(config) = cache.get(host_name) {
+            return Ok(config.clone());
+        }
+
+        let key = KeyPair::generate().map_err(io::Error::other)?;
+        let certificate = Self::leaf_parameters(host_name)?
+            .signed_by(&key, &self.certificate, &self.key)
+            .map_err(io::Error::other)?;
+        let chain = vec![certificate.der().clone(), CertificateDer::from(self.certificate.der().to_vec())];
+        let private_key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+
+        let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_single_cert(chain, private_key)
+            .map_err(io::Error::other)?;
+        let config = Arc::new(config);
+        cache.insert(host_name.to_string(), config.clone());
+        return Ok(config);
+    }
+
+    fn root_parameters() -> CertificateParams {
+        let mut distinguished_name = DistinguishedName::new();
+        distinguished_name.push(DnType::OrganizationName, "Proxy");
+        distinguished_name.push(DnType::CommonName, "Proxy Interception CA");
+
+        let mut params = CertificateParams::default();
+        params.distinguished_name = distinguished_name;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign, KeyUsagePurpose::DigitalSignature];
+        return params;
+    }
+
+    fn leaf_parameters(host_name: &str) -> io::Result<CertificateParams> {
+        let mut params = CertificateParams::new(vec![host_name.to_string()]).map_err(io::Error::other)?;
+        let mut distinguished_name = DistinguishedName::new();
+        distinguished_name.push(DnType::CommonName, host_name);
+        params.distinguished_name = distinguished_name;
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+
+        // Some clients reject leaf certificates valid for longer than 825 days.
+        let now = OffsetDateTime::now_utc();
+        params.not_before = now - Duration::days(1);
+        params.not_after = now + Duration::days(365);
+
+        let nanos = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(n) => n.as_nanos(),
+            Err(_) => 0,
+        };
+        params.serial_number = Some(SerialNumber::from_slice(&nanos.to_be_bytes()));
+        return Ok(params);
+    }
+}
+
+// Create the file readable only by its owner, as it holds a private key.
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    return file.sync_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn key_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let directory = std::env::temp_dir().join(format!("proxy-ca-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        CertificateAuthority::load_or_generate(&directory).unwrap();
+        let mode = fs::metadata(directory.join(KEY_FILE)).unwrap().permissions().mode();
+        // The saved key is loaded rather than replaced.
+        CertificateAuthority::load_or_generate(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
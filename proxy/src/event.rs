@@ -0,0 +1,55 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+
+use parse::flow::Flow;
+
+use crate::hold::Held;
+
+// Everything the proxy reports to its owner. Failures are reported rather than ending the proxy,
+// except when the listener cannot be bound.
+pub enum ProxyEvent {
+    // A snapshot of a new or updated flow. Later snapshots with the same ID replace earlier ones.
+    Flow(Box<Flow>),
+    // A message held until a verdict is given through the proxy handle.
+    Held(Held),
+    // A request re-sent by the repeater with the response received, having the ID of the flow
+    // the request was captured in.
+    Repeated(Box<Flow>),
+    Error(ProxyError),
+}
+
+#[derive(Debug)]
+pub enum ProxyError {
+    // The listening address could not be bound, so no clients will be served.
+    Bind { address: String, error: io::Error },
+    Accept(io::Error),
+    // The host of the origin server could not be resolved.
+    Resolve { authority: String, error: io::Error },
+    Connect { authority: String, error: io::Error },
+    // A TLS session with the client or the origin server failed.
+    Tls { host: String, reason: String },
+    // The client closed the connection abruptly.
+    ClientReset { client: SocketAddr, error: io::Error },
+    // The timeouts of an accepted connection could not be set, so it was dropped.
+    Timeout { client: SocketAddr, error: io::Error },
+    // The request of the flow could not be sent again or was not answered.
+    Repeat { flow: u64, reason: String },
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ProxyError::Bind { address, error } => write!(f, "Failed to listen on {}: {}", address, error),
+            ProxyError::Accept(error) => write!(f, "Failed to accept a connection: {}", error),
+            ProxyError::Resolve { authority, error } => write!(f, "Failed to resolve {}: {}", authority, error),
+            ProxyError::Connect { authority, error } => write!(f, "Failed to connect to {}: {}", authority, error),
+            ProxyError::Tls { host, reason } => write!(f, "TLS with {} failed: {}", host, reason),
+            ProxyError::ClientReset { client, error } => write!(f, "Connection from {} reset: {}", client, error),
+            ProxyError::Timeout { client, error } => write!(f, "Failed to set timeouts for connection from {}: {}", client, error),
+            ProxyError::Repeat { flow, reason } => write!(f, "Failed to repeat #{}: {}", flow, reason),
+        }
+    }
+}
+
+impl std::error::Error for ProxyError {}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+
+use parse::flow::Flow;
+
+use crate::event::ProxyEvent;
+use crate::Reporter;
+
+// Selects the messages held for a verdict before being sent on.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct HoldRules {
+    pub requests: bool,
+    pub responses: bool,
+    // Only exchanges whose request contains the pattern are held. An empty pattern matches every
+    // request.
+    pub pattern: String,
+}
+
+impl HoldRules {
+    pub fn matches(&self, request: &[u8]) -> bool {
+        let pattern = self.pattern.as_bytes();
+        pattern.is_empty() || request.windows(pattern.len()).any(|window| window == pattern)
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+// A message waiting for a verdict. The flow ID identifies the exchange it belongs to.
+#[derive(Clone,Debug)]
+pub struct Held {
+    pub id: u64,
+    pub flow: u64,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Verdict {
+    Forward,
+    // Send the data in place of the held message.
+    Edited(Vec<u8>),
+    // Discard the message and close the client connection.
+    Drop,
+}
+
+// Messages held by a proxy, each waiting on its own channel for a verdict.
+pub(crate) struct Holds {
+    rules: Mutex<HoldRules>,
+    waiting: Mutex<HashMap<u64, mpsc::Sender<Verdict>>>,
+    next_id: AtomicU64,
+}
+
+impl Holds {
+    pub fn new(rules: HoldRules) -> Self {
+        Holds {
+            rules: Mutex::new(rules),
+            waiting: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn rules(&self) -> HoldRules {
+        self.rules.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    // Messages already held stay held until resolved, unless holding is turned off entirely.
+    pub fn set_rules(&self, rules: HoldRules) {
+        let off = !rules.requests && !rules.responses;
+        *self.rules.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = rules;
+        if off {
+            self.release_all();
+        }
+    }
+
+    // Whether a message sent in the direction for the request should be held.
+    pub fn applies(&self, direction: Direction, request: &[u8]) -> bool {
+        let rules = self.rules.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let enabled = match direction {
+            Direction::Request => rules.requests,
+            Direction::Response => rules.responses,
+        };
+        return enabled && rules.matches(request);
+    }
+
+    // Report the message and block until a verdict is given. Messages are forwarded if the
+    // verdict can no longer be given.
+    pub fn hold(&self, flow: &Flow, direction: Direction, data: &[u8], reporter: &Reporter) -> Verdict {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        self.waiting.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(id, sender);
+
+        let held = Held { id: id, flow: flow.id, direction: direction, data: data.to_vec() };
+        match reporter.sender.send(ProxyEvent::Held(held)) {
+            Ok(_) => {},
+            Err(_) => {
+                self.resolve(id, Verdict::Forward);
+            },
+        };
+        return receiver.recv().unwrap_or(Verdict::Forward);
+    }
+
+    // Returns false if no message is held with the ID.
+    pub fn resolve(&self, id: u64, verdict: Verdict) -> bool {
+        let sender = self.waiting.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&id);
+        match sender {
+            Some(sender) => sender.send(verdict).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn release_all(&self) {
+        let waiting: Vec<_> = self.waiting.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).drain().collect();
+        for (_, sender) in waiting {
+            let _ = sender.send(Verdict::Forward);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hold_rules_pattern() {
+        let mut rules = HoldRules { requests: true, responses: false, pattern: String::new() };
+        assert!(rules.matches(b"GET / HTTP/1.1\r\n\r\n"));
+        rules.pattern = String::from("Host: example.com");
+        assert!(rules.matches(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"));
+        assert!(!rules.matches(b"GET / HTTP/1.1\r\nHost: example.org\r\n\r\n"));
+    }
+}
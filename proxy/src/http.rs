@@ -0,0 +1,421 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+use parse::flow::{Flow, Origin};
+use parse::http_reader::{Framing, MessageReader};
+use parse::http_request::HTTPRequest;
+use parse::http_response::HTTPResponse;
+
+use crate::mitm;
+use crate::tunnel;
+use crate::event::ProxyError;
+use crate::hold::{Direction, Holds, Verdict};
+use crate::{connect, Config, Reporter, is_reset, next_connection_id, next_flow_id, report, report_error, timestamp};
+
+// RFC 9110 7.6.1 - Hop-by-hop fields are meaningful only for a single connection.
+const HOP_BY_HOP_HEADERS: [&str; 3] = ["Connection", "Proxy-Connection", "Keep-Alive"];
+
+pub const BAD_REQUEST: &[u8] = b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+pub const BAD_GATEWAY: &[u8] = b"HTTP/1.1 502 Bad Gateway\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+pub const CONNECTION_ESTABLISHED: &[u8] = b"HTTP/1.1 200 Connection Established\r\n\r\n";
+
+// Relay HTTP/1.1 requests from the client to origin servers and stream the responses back until
+// either side closes the connection. Each exchange is reported as it progresses.
+// CONNECT requests open a tunnel, which is intercepted when a certificate authority is available.
+pub fn forward(mut client: TcpStream, client_address: SocketAddr, config: &Config, reporter: &Reporter) {
+    let mut reader = MessageReader::request();
+    let connect = |address: &str| {
+        let upstream = connect(address, config)?;
+        let upstream_address = upstream.peer_addr().ok();
+        return Ok((upstream, upstream_address));
+    };
+    let peer = Peer { connection: next_connection_id(reporter), address: client_address, server_name: None };
+    let (request, flow) = match serve(&mut client, &mut reader, peer, upstream_address, connect, &config.holds, reporter) {
+        Some(tunnel) => tunnel,
+        None => return,
+    };
+
+    // Bytes following the CONNECT request belong to the tunnel.
+    let early_data = reader.take_buffered();
+    match config.authority {
+        Some(ref authority) => mitm::intercept(client, &request, &early_data, flow, config, authority, reporter),
+        None => tunnel::open(client, &request, &early_data, flow, config, reporter),
+    };
+}
+
+// The client connection requests are read from.
+pub struct Peer {
+    pub connection: u64,
+    pub address: SocketAddr,
+    // Server name of the intercepted TLS session requests are read from.
+    pub server_name: Option<String>,
+}
+
+// RFC 9112 9.3 - Read requests from a persistent client connection, including pipelined requests,
+// and relay each one to the origin server named by the address function. Upstream connections are
+// opened with the connect function and reused while requests go to the same server. Stops when
+// either peer closes the connection, returning a CONNECT request and its flow to the caller.
+pub fn serve<C, U, A, F>(client: &mut C, reader: &mut MessageReader, peer: Peer, address: A, mut connect: F, holds: &Holds, reporter: &Reporter) -> Option<(HTTPRequest, Flow)>
+where
+    C: Read + Write,
+    U: Read + Write,
+    A: Fn(&HTTPRequest) -> Option<String>,
+    F: FnMut(&str) -> Result<(U, Option<SocketAddr>), ProxyError>,
+{
+    let mut upstream: Option<(String, U, Option<SocketAddr>)> = None;
+    loop {
+        let request_timestamp = timestamp();
+        let (data, complete) = match read_message(client, reader) {
+            Ok(Message::Complete(data)) => (data, true),
+            Ok(Message::Malformed(data)) => (data, false),
+            Ok(Message::Closed) => return None,
+            Err(error) => {
+                if is_reset(&error) {
+                    report_error(reporter, ProxyError::ClientReset { client: peer.address, error: error });
+                }
+                return None;
+            },
+        };
+
+        let mut head_length = head_length(&data).unwrap_or(data.len());
+        let mut flow = Flow::new(next_flow_id(reporter), peer.connection, peer.address, parse::Request::new(request_timestamp, data.clone()));
+        report(reporter, &flow);
+
+        let mut request = match HTTPRequest::new(&data[..head_length]) {
+            Ok(request) if complete => request,
+            _ => {
+                respond(client, BAD_REQUEST, &mut flow, reporter);
+                return None;
+            },
+        };
+        if request.method() == "CONNECT" {
+            return Some((request, flow));
+        }
+
+        // Rules apply to the request as sent by the client, even once edited.
+        let hold_response = holds.applies(Direction::Response, &data);
+        let mut data = data;
+        if holds.applies(Direction::Request, &data) {
+            match holds.hold(&flow, Direction::Request, &data, reporter) {
+                Verdict::Forward => {},
+                Verdict::Edited(edited) => {
+                    head_length = self::head_length(&edited).unwrap_or(edited.len());
+                    flow.edit_request(edited.clone());
+                    report(reporter, &flow);
+                    request = match HTTPRequest::new(&edited[..head_length]) {
+                        Ok(request) => request,
+                        Err(_) => {
+                            respond(client, BAD_REQUEST, &mut flow, reporter);
+                            return None;
+                        },
+                    };
+                    data = edited;
+                },
+                Verdict::Drop => {
+                    flow.complete(timestamp());
+                    report(reporter, &flow);
+                    return None;
+                },
+            };
+        }
+        let origin = match address(&request) {
+            Some(origin) => origin,
+            None => {
+                respond(client, BAD_REQUEST, &mut flow, reporter);
+                return None;
+            },
+        };
+        flow.origin = Some(Origin { authority: origin.clone(), server_name: peer.server_name.clone() });
+
+        // A connection to a different server is closed rather than kept idle.
+        if let Some((ref current, _, _)) = upstream {
+            if *current != origin {
+                upstream = None;
+            }
+        }
+        let mut reused = upstream.is_some();
+        let persistent = loop {
+            let (_, stream, upstream_address) = match upstream {
+                Some(ref mut upstream) => upstream,
+                None => match connect(&origin) {
+                    Ok((stream, upstream_address)) => upstream.insert((origin.clone(), stream, upstream_address)),
+                    Err(error) => {
+                        report_error(reporter, error);
+                        respond(client, BAD_GATEWAY, &mut flow, reporter);
+                        return None;
+                    },
+                },
+            };
+            flow.connected(timestamp(), *upstream_address);
+            let holds = if hold_response { Some(holds) } else { None };
+            match relay(client, &request, &data[head_length..], stream, &mut flow, holds, reporter) {
+                Some(persistent) => break persistent,
+                // The server may have closed an idle connection before receiving the request. Only
+                // requests that can safely be sent twice are retried.
+                None if reused && is_idempotent(request.method()) => {
+                    upstream = None;
+                    reused = false;
+                },
+                None => {
+                    respond(client, BAD_GATEWAY, &mut flow, reporter);
+                    return None;
+                },
+            };
+        };
+
+        // A response delimited by closing the connection, or announcing that the server will
+        // close it, ends the client connection as well.
+        if !persistent {
+            return None;
+        }
+        if !is_persistent(request.version(), request.header("Connection")) {
+            return None;
+        }
+    }
+}
+
+// Send the request to the origin server and stream the response back to the client until the
+// final response has been received. Responses are held when given the holds and sent once the
+// verdict is given. Returns whether the upstream connection can carry another request, or None if
+// the server closed the connection without sending a response.
+pub fn relay<C: Write, U: Read + Write>(client: &mut C, request: &HTTPRequest, body: &[u8], upstream: &mut U, flow: &mut Flow, holds: Option<&Holds>, reporter: &Reporter) -> Option<bool> {
+    if upstream.write_all(&rewrite(request, body)).and_then(|_| upstream.flush()).is_err() {
+        return None;
+    }
+
+    let mut held = Vec::new();
+    let mut reader = MessageReader::response();
+    reader.set_request_method(request.method());
+    let mut buf = [0u8; 4096];
+    let mut persistent = 'read: loop {
+        // RFC 9110 15.2 - Interim responses precede the final response.
+        while let Some(response) = reader.next_message() {
+            if !is_interim(&response) {
+                break 'read is_persistent_response(&response);
+            }
+        }
+        match upstream.read(&mut buf) {
+            Ok(0) => break false,
+            Ok(n) => {
+                reader.push(&buf[..n]);
+                flow.extend_response(timestamp(), &buf[..n]);
+                if holds.is_some() {
+                    held.extend_from_slice(&buf[..n]);
+                } else if let Err(error) = client.write_all(&buf[..n]).and_then(|_| client.flush()) {
+                    if is_reset(&error) {
+                        report_error(reporter, ProxyError::ClientReset { client: flow.client, error: error });
+                    }
+                    break false;
+                }
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break false,
+        };
+    };
+
+    flow.response.as_ref()?;
+    flow.complete(timestamp());
+    report(reporter, flow);
+
+    if let Some(holds) = holds {
+        let response = match holds.hold(flow, Direction::Response, &held, reporter) {
+            Verdict::Forward => held,
+            Verdict::Edited(edited) => {
+                flow.edit_response(edited.clone());
+                report(reporter, flow);
+                // The edited response may be framed differently.
+                persistent = persistent && is_persistent_response(&edited);
+                edited
+            },
+            Verdict::Drop => return Some(false),
+        };
+        if client.write_all(&response).and_then(|_| client.flush()).is_err() {
+            return Some(false);
+        }
+    }
+    return Some(persistent);
+}
+
+// Whether another request can follow the response on the same connection. Switching protocols
+// hands the connection over to another protocol.
+fn is_persistent_response(response: &[u8]) -> bool {
+    match HTTPResponse::new(response) {
+        Some(response) if response.status_code() != 101 => is_persistent(response.version(), response.header("Connection")),
+        _ => false,
+    }
+}
+
+// Answer the client on behalf of the origin server, recording the response in the flow.
+pub fn respond<C: Write>(client: &mut C, response: &[u8], flow: &mut Flow, reporter: &Reporter) {
+    let _ = client.write_all(response).and_then(|_| client.flush());
+    flow.extend_response(timestamp(), response);
+    flow.complete(timestamp());
+    report(reporter, flow);
+}
+
+pub enum Message {
+    Complete(Vec<u8>),
+    // Bytes that cannot be delimited as a message, or a message cut short by the peer closing the
+    // connection.
+    Malformed(Vec<u8>),
+    Closed,
+}
+
+// Read the next message from the stream, buffering any bytes received after it in the reader.
+pub fn read_message<S: Read>(stream: &mut S, reader: &mut MessageReader) -> io::Result<Message> {
+    let mut buf = [0u8; 4096];
+    loop {
+        match reader.framing() {
+            Framing::Complete(_) => {
+                if let Some(message) = reader.next_message() {
+                    return Ok(Message::Complete(message));
+                }
+            },
+            Framing::Invalid => return Ok(Message::Malformed(reader.take_buffered())),
+            Framing::Incomplete | Framing::UntilClose => {},
+        };
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            return match reader.framing() {
+                Framing::UntilClose => Ok(reader.finish().map(Message::Complete).unwrap_or(Message::Closed)),
+                _ => Ok(reader.finish().map(Message::Malformed).unwrap_or(Message::Closed)),
+            };
+        }
+        reader.push(&buf[..n]);
+    }
+}
+
+// 1xx responses other than 101 Switching Protocols are followed by another response.
+pub fn is_interim(response: &[u8]) -> bool {
+    match HTTPResponse::new(response) {
+        Some(response) => response.status_code() < 200 && response.status_code() != 101,
+        None => false,
+    }
+}
+
+// Length of the request line and field lines including the empty line terminating them.
+pub fn head_length(data: &[u8]) -> Option<usize> {
+    data.windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|i| i + 4)
+}
+
+// RFC 9112 9.3 - HTTP/1.1 connections persist unless either peer sends the close option, while
+// HTTP/1.0 connections persist only when kept alive.
+fn is_persistent(version: &str, connection: Option<&str>) -> bool {
+    let options = connection.unwrap_or("");
+    if options.split(',').any(|option| option.trim().eq_ignore_ascii_case("close")) {
+        return false;
+    }
+    return version == "HTTP/1.1" || options.split(',').any(|option| option.trim().eq_ignore_ascii_case("keep-alive"));
+}
+
+// RFC 9110 9.2.2 - Methods whose intended effect is the same when the request is repeated.
+fn is_idempotent(method: &str) -> bool {
+    return ["GET", "HEAD", "OPTIONS", "TRACE", "PUT", "DELETE"].contains(&method);
+}
+
+// Resolve the origin server from an absolute-form target or the Host header, defaulting to port 80.
+fn upstream_address(request: &HTTPRequest) -> Option<String> {
+    let authority = match request.target().strip_prefix("http://") {
+        Some(rest) => rest.split('/').next()?,
+        None => request.header("Host")?,
+    };
+    // Discard any userinfo preceding the host.
+    let authority = authority.rsplit('@').next()?;
+    if authority.is_empty() {
+        return None;
+    }
+
+    // The port follows the last colon unless the colon is part of an IPv6 literal.
+    let has_port = match authority.rfind(':') {
+        Some(i) => !authority[i..].contains(']'),
+        None => false,
+    };
+    if has_port {
+        return Some(authority.to_string());
+    } else {
+        return Some(format!("{}:80", authority));
+    }
+}
+
+// Reduce an absolute-form target to the origin-form expected by origin servers.
+fn origin_form(target: &str) -> String {
+    match target.strip_prefix("http://") {
+        Some(rest) => match rest.find('/') {
+            Some(i) => rest[i..].to_string(),
+            None => String::from("/"),
+        },
+        None => target.to_string(),
+    }
+}
+
+// Rebuild the request for the origin server without hop-by-hop fields, including those named by
+// the Connection header. The upstream connection is managed separately from the client's.
+pub fn rewrite(request: &HTTPRequest, body: &[u8]) -> Vec<u8> {
+    let connection_options: Vec<String> = request.header("Connection")
+        .map(|options| options.split(',').map(|option| option.trim().to_string()).collect())
+        .unwrap_or_default();
+    let mut rewritten = request.clone();
+    rewritten.set_target(&origin_form(request.target()));
+    for header in HOP_BY_HOP_HEADERS.iter().copied().chain(connection_options.iter().map(|option| option.as_str())) {
+        rewritten.remove_header(header);
+    }
+    // RFC 9112 6.3 - Transfer-Encoding overrides Content-Length, which is removed so the origin
+    // server cannot delimit the body differently.
+    if request.header("Transfer-Encoding").is_some() {
+        rewritten.remove_header("Content-Length");
+    }
+    rewritten.set_body(body.to_vec());
+    return rewritten.to_bytes();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_form_upstream() {
+        let request = HTTPRequest::new(b"GET http://example.com:8080/index.html HTTP/1.1\r\nHost: example.com:8080\r\n\r\n").unwrap();
+        assert_eq!(upstream_address(&request), Some(String::from("example.com:8080")));
+        assert_eq!(origin_form(request.target()), "/index.html");
+    }
+
+    #[test]
+    fn host_header_upstream() {
+        let request = HTTPRequest::new(b"GET / HTTP/1.1\r\nhost: [::1]\r\n\r\n").unwrap();
+        assert_eq!(upstream_address(&request), Some(String::from("[::1]:80")));
+    }
+
+    #[test]
+    fn rewrite_hop_by_hop() {
+        let request = HTTPRequest::new(b"POST http://example.com HTTP/1.1\r\nHost: example.com\r\nProxy-Connection: keep-alive\r\nConnection: X-Trace\r\nX-Trace: 1\r\nContent-Length: 4\r\n\r\n").unwrap();
+        let expected = "POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\n\r\nAAAA";
+        assert_eq!(rewrite(&request, b"AAAA"), expected.as_bytes());
+    }
+
+    #[test]
+    fn rewrite_chunked_with_content_length() {
+        let request = HTTPRequest::new(b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 2\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+        let expected = "POST / HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nAAAA\r\n0\r\n\r\n";
+        assert_eq!(rewrite(&request, b"4\r\nAAAA\r\n0\r\n\r\n"), expected.as_bytes());
+    }
+
+    #[test]
+    fn idempotent_methods() {
+        assert!(is_idempotent("GET"));
+        assert!(is_idempotent("DELETE"));
+        assert!(!is_idempotent("POST"));
+        assert!(!is_idempotent("PATCH"));
+        assert!(!is_idempotent("get"));
+    }
+
+    #[test]
+    fn persistent_connections() {
+        assert!(is_persistent("HTTP/1.1", None));
+        assert!(!is_persistent("HTTP/1.1", Some("Upgrade, close")));
+        assert!(!is_persistent("HTTP/1.0", None));
+        assert!(is_persistent("HTTP/1.0", Some("Keep-Alive")));
+    }
+}
@@ -1,45 +1,263 @@
-use std::time::SystemTime;
-use std::io::{Read, Write};
-use std::io::BufReader;
-use std::net::TcpListener;
+use std::io;
+use std::io::Write;
+use std::time::{Duration, SystemTime};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
 use std::thread;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
 
-use parse;
+use parse::flow::Flow;
+use parse::http_reader::MessageReader;
+use parse::http_response::HTTPResponse;
 
-pub fn start(address: &'static str, sender: mpsc::Sender<parse::Request>) -> thread::JoinHandle<()> {
-    thread::spawn(move || {
-        let listener = TcpListener::bind(address).unwrap();
-        loop {
-            match listener.accept() {
-                Ok((mut stream, _addr)) => {
-                    let timestamp = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-                        Ok(n) => n.as_millis(),
-                        Err(_) => 0,
-                    };
-                    // An HTTP request will keep the connection open while waiting for a response.
-                    let mut buf_reader = BufReader::new(&stream);
-                    let mut body = Vec::new();
-                    let mut buf = [0u8; 1024];
-                    loop {
-                        match buf_reader.read(&mut buf) {
-                            Ok(n) => {
-                                body.extend_from_slice(&buf[..n]);
-                                if n <= 1024 {
-                                    break;
-                                }
-                            },
-                            Err(_) => { },
-                        };
-                    }
-                    match sender.send(parse::Request::new(timestamp, body)) {
-                        Ok(_) => {},
-                        Err(_) => {},
-                    };
-                    stream.write_all("HTTP/1.1 200 OK\r\n\r\n".as_bytes()).unwrap();
-                },
-                Err(_) => { },
-            };
+pub mod builder;
+pub mod ca;
+pub mod event;
+pub mod hold;
+mod http;
+mod mitm;
+mod repeater;
+mod tunnel;
+
+use crate::builder::ProxyBuilder;
+use crate::ca::CertificateAuthority;
+use crate::event::{ProxyError, ProxyEvent};
+use crate::hold::Holds;
+
+// Bounds on the resources used to serve clients.
+#[derive(Clone,Debug)]
+pub struct Limits {
+    // Connections served at once. Further clients wait to be accepted.
+    pub max_connections: usize,
+    // Time allowed to connect to a peer or for a peer to accept or send data before the connection
+    // is closed. None waits indefinitely.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_connections: 128,
+            timeout: Some(Duration::from_secs(60)),
         }
+    }
+}
+
+// Serve clients on the address with the default limits until the process exits. Failures are
+// reported to the sender.
+pub fn start(address: &str, sender: mpsc::Sender<ProxyEvent>) -> thread::JoinHandle<()> {
+    run(ProxyBuilder::new().listen(address), sender)
+}
+
+// Like start, but TLS in CONNECT tunnels is terminated using certificates issued by the authority.
+pub fn start_intercepting(address: &str, authority: CertificateAuthority, sender: mpsc::Sender<ProxyEvent>) -> thread::JoinHandle<()> {
+    run(ProxyBuilder::new().listen(address).intercept(authority), sender)
+}
+
+fn run(builder: ProxyBuilder, sender: mpsc::Sender<ProxyEvent>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        match builder.start(sender.clone()) {
+            Ok(proxy) => proxy.wait(),
+            Err(error) => match sender.send(ProxyEvent::Error(error)) {
+                Ok(_) => {},
+                Err(_) => {},
+            },
+        };
     })
 }
+
+// Settings shared by every connection served by a proxy.
+struct Config {
+    authority: Option<CertificateAuthority>,
+    limits: Limits,
+    // HTTP proxy through which connections to origin servers are tunnelled.
+    upstream_proxy: Option<String>,
+    holds: Holds,
+}
+
+// Delivers events to the owner of a proxy and counts what was served.
+#[derive(Clone)]
+struct Reporter {
+    sender: mpsc::Sender<ProxyEvent>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    connections: AtomicU64,
+    flows: AtomicU64,
+    errors: AtomicU64,
+}
+
+// Accept clients until stopped, serving each connection on its own thread.
+fn listen(listener: TcpListener, stopped: Arc<AtomicBool>, slots: Arc<Slots>, config: Arc<Config>, reporter: Reporter) {
+    loop {
+        // Wait for a connection to finish before accepting more than the limit.
+        let slot = match Slots::acquire(&slots, &stopped) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let accepted = listener.accept();
+        if stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        let (stream, addr) = match accepted {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                report_error(&reporter, ProxyError::Accept(error));
+                continue;
+            },
+        };
+        let timeout = config.limits.timeout;
+        if let Err(error) = stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)) {
+            report_error(&reporter, ProxyError::Timeout { client: addr, error: error });
+            continue;
+        }
+        let config = config.clone();
+        let reporter = reporter.clone();
+        thread::spawn(move || {
+            http::forward(stream, addr, &config, &reporter);
+            drop(slot);
+        });
+    }
+}
+
+// Counts the connections being served so that no more than the limit are served at once.
+struct Slots {
+    used: Mutex<usize>,
+    released: Condvar,
+    limit: usize,
+}
+
+impl Slots {
+    fn used(&self) -> usize {
+        *self.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn new(limit: usize) -> Self {
+        Slots {
+            used: Mutex::new(0),
+            released: Condvar::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    // Returns None if the listener is stopped while waiting for a slot.
+    fn acquire(slots: &Arc<Slots>, stopped: &AtomicBool) -> Option<Slot> {
+        let mut used = slots.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        while *used >= slots.limit {
+            if stopped.load(Ordering::SeqCst) {
+                return None;
+            }
+            used = slots.released.wait(used).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *used += 1;
+        return Some(Slot { slots: slots.clone() });
+    }
+}
+
+// A connection being served. The slot is released when dropped, even if serving panics.
+struct Slot {
+    slots: Arc<Slots>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut used = self.slots.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *used -= 1;
+        self.slots.released.notify_one();
+    }
+}
+
+// Open a connection to the authority, through the upstream proxy if one is configured.
+fn connect(authority: &str, config: &Config) -> Result<TcpStream, ProxyError> {
+    let upstream_proxy = match config.upstream_proxy {
+        Some(ref upstream_proxy) => upstream_proxy,
+        None => return connect_directly(authority, config.limits.timeout),
+    };
+    let mut stream = connect_directly(upstream_proxy, config.limits.timeout)?;
+    let refused = |reason: String| ProxyError::Connect {
+        authority: authority.to_string(),
+        error: io::Error::new(io::ErrorKind::ConnectionRefused, format!("{} via {}", reason, upstream_proxy)),
+    };
+
+    // RFC 9110 9.3.6 - Ask the upstream proxy for a tunnel to the origin server.
+    let request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n", authority, authority);
+    if let Err(error) = stream.write_all(request.as_bytes()) {
+        return Err(refused(error.to_string()));
+    }
+    let mut reader = MessageReader::response();
+    reader.set_request_method("CONNECT");
+    let response = match http::read_message(&mut stream, &mut reader) {
+        Ok(http::Message::Complete(response)) => HTTPResponse::new(&response),
+        Ok(_) => None,
+        Err(error) => return Err(refused(error.to_string())),
+    };
+    match response {
+        Some(response) if response.status_code() / 100 == 2 => return Ok(stream),
+        Some(response) => return Err(refused(format!("{} {}", response.status_code(), response.reason()))),
+        None => return Err(refused(String::from("invalid response"))),
+    };
+}
+
+// Connect to the first reachable address the authority resolves to, applying the timeout to
+// connecting and to every later read and write.
+fn connect_directly(authority: &str, timeout: Option<Duration>) -> Result<TcpStream, ProxyError> {
+    let addresses = match authority.to_socket_addrs() {
+        Ok(addresses) => addresses,
+        Err(error) => return Err(ProxyError::Resolve { authority: authority.to_string(), error: error }),
+    };
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no addresses resolved");
+    for address in addresses {
+        let stream = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&address, timeout),
+            None => TcpStream::connect(address),
+        };
+        match stream {
+            Ok(stream) => match stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)) {
+                Ok(_) => return Ok(stream),
+                Err(e) => error = e,
+            },
+            Err(e) => error = e,
+        };
+    }
+    return Err(ProxyError::Connect { authority: authority.to_string(), error: error });
+}
+
+// Milliseconds since the Unix epoch used to timestamp captured data.
+fn timestamp() -> u128 {
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(n) => n.as_millis(),
+        Err(_) => 0,
+    }
+}
+
+// IDs are numbered from 1 for each proxy, so they also count the connections and flows served.
+fn next_connection_id(reporter: &Reporter) -> u64 {
+    reporter.counters.connections.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+fn next_flow_id(reporter: &Reporter) -> u64 {
+    reporter.counters.flows.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+// Send a snapshot of the flow. Receivers replace earlier snapshots with the same ID.
+fn report(reporter: &Reporter, flow: &Flow) {
+    match reporter.sender.send(ProxyEvent::Flow(Box::new(flow.clone()))) {
+        Ok(_) => {},
+        Err(_) => {},
+    };
+}
+
+fn report_error(reporter: &Reporter, error: ProxyError) {
+    reporter.counters.errors.fetch_add(1, Ordering::Relaxed);
+    match reporter.sender.send(ProxyEvent::Error(error)) {
+        Ok(_) => {},
+        Err(_) => {},
+    };
+}
+
+// Errors on a client connection that mean the client has gone away.
+fn is_reset(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe)
+}
@@ -0,0 +1,219 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, OnceLock};
+
+use parse::flow::Flow;
+use parse::http_reader::MessageReader;
+use parse::http_request::HTTPRequest;
+use parse::error::Reason;
+use parse::tls_request::{Message, RecordReader};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConnection, StreamOwned};
+
+use crate::ca::CertificateAuthority;
+use crate::http;
+use crate::tunnel;
+use crate::event::ProxyError;
+use crate::{connect, Config, Reporter, next_flow_id, report, report_error, timestamp};
+
+// TLS record header: content type, legacy record version and length.
+const RECORD_HEADER_LENGTH: usize = 5;
+// Bytes read while waiting for the rest of a fragmented ClientHello before handing it to the TLS
+// session as it is.
+const MAX_HELLO_LENGTH: usize = 0x10000;
+
+// Terminate TLS from the client of a CONNECT tunnel with a certificate issued for the requested
+// host and open separate TLS sessions to the origin server. The ClientHello and the decrypted
+// exchanges are reported. Tunnels not starting with a TLS handshake are relayed untouched.
+pub fn intercept(mut client: TcpStream, request: &HTTPRequest, early_data: &[u8], mut flow: Flow, config: &Config, authority: &CertificateAuthority, reporter: &Reporter) {
+    let upstream = match connect(request.target(), config) {
+        Ok(upstream) => upstream,
+        Err(error) => {
+            report_error(reporter, error);
+            return http::respond(&mut client, http::BAD_GATEWAY, &mut flow, reporter);
+        },
+    };
+    let upstream_address = upstream.peer_addr().ok();
+    flow.connected(timestamp(), upstream_address);
+    http::respond(&mut client, http::CONNECTION_ESTABLISHED, &mut flow, reporter);
+
+    let hello = match read_hello(&mut client, early_data) {
+        Some(hello) => hello,
+        None => return,
+    };
+    // RFC 8446 5.1 - Handshake records have content type 22.
+    if hello[0] != 22 {
+        return tunnel::relay(client, upstream, &hello, flow.connection, reporter);
+    }
+    let mut hello_flow = Flow::new(next_flow_id(reporter), flow.connection, flow.client, parse::Request::new(timestamp(), hello.clone()));
+    hello_flow.connected(timestamp(), upstream_address);
+    report(reporter, &hello_flow);
+
+    // Prefer the Server Name Indication over the CONNECT target, which may be an IP address.
+    let host_name = server_name(&hello).unwrap_or_else(|| host(request.target()).to_string());
+
+    let tls_error = |reason: String| ProxyError::Tls { host: host_name.clone(), reason: reason };
+    let server_config = match authority.server_config(&host_name) {
+        Ok(server_config) => server_config,
+        Err(error) => return report_error(reporter, tls_error(error.to_string())),
+    };
+    let mut connection = match ServerConnection::new(server_config) {
+        Ok(connection) => connection,
+        Err(error) => return report_error(reporter, tls_error(error.to_string())),
+    };
+    // Hand the ClientHello already read from the socket to the TLS session and complete the
+    // handshake so that failures are told apart from the client closing the connection.
+    let mut remaining = &hello[..];
+    while !remaining.is_empty() {
+        if let Err(error) = connection.read_tls(&mut remaining) {
+            return report_error(reporter, tls_error(error.to_string()));
+        }
+    }
+    if let Err(error) = connection.process_new_packets() {
+        return report_error(reporter, tls_error(error.to_string()));
+    }
+    let mut client = StreamOwned::new(connection, client);
+    if let Err(error) = client.conn.complete_io(&mut client.sock) {
+        return report_error(reporter, tls_error(error.to_string()));
+    }
+
+    let server_name = match ServerName::try_from(host_name.clone()) {
+        Ok(server_name) => server_name,
+        Err(error) => return report_error(reporter, tls_error(error.to_string())),
+    };
+    // The connection opened before answering the CONNECT request carries the first TLS session.
+    // Later sessions reconnect if the origin server closes it.
+    let mut upstream = Some(upstream);
+    let connect = |address: &str| {
+        let mut upstream = match upstream.take() {
+            Some(upstream) => upstream,
+            None => connect(address, config)?,
+        };
+        let upstream_address = upstream.peer_addr().ok();
+        let mut connection = ClientConnection::new(client_config(), server_name.clone())
+            .map_err(|error| tls_error(error.to_string()))?;
+        connection.complete_io(&mut upstream).map_err(|error| tls_error(error.to_string()))?;
+        return Ok((StreamOwned::new(connection, upstream), upstream_address));
+    };
+
+    let mut reader = MessageReader::request();
+    let target = request.target().to_string();
+    let tunnel = http::serve(&mut client, &mut reader, http::Peer { connection: flow.connection, address: flow.client, server_name: Some(host_name.clone()) }, |_| Some(target.clone()), connect, &config.holds, reporter);
+    // Tunnels cannot be nested inside an intercepted tunnel.
+    if let Some((_, mut flow)) = tunnel {
+        http::respond(&mut client, http::BAD_REQUEST, &mut flow, reporter);
+    }
+    client.conn.send_close_notify();
+    let _ = client.flush();
+}
+
+// Read the handshake records holding the ClientHello, starting with any bytes already received.
+// Data not forming a TLS record is returned as soon as it is recognised.
+fn read_hello(stream: &mut TcpStream, early_data: &[u8]) -> Option<Vec<u8>> {
+    let mut data = early_data.to_vec();
+    let mut buf = [0u8; 4096];
+    while !hello_complete(&data) {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+        };
+    }
+    return Some(data);
+}
+
+// Whether the data starts with something other than a handshake record, or holds whole handshake
+// records ending with a whole handshake message. RFC 8446 5.1 - A handshake message may be
+// fragmented across records.
+fn hello_complete(data: &[u8]) -> bool {
+    if data.len() > MAX_HELLO_LENGTH {
+        return true;
+    }
+    let mut end = 0;
+    while end < data.len() {
+        if data[end] != 22 {
+            return true;
+        }
+        match data.get(end + 3..end + RECORD_HEADER_LENGTH) {
+            Some(length) => end += RECORD_HEADER_LENGTH + usize::from(u16::from_be_bytes([length[0], length[1]])),
+            None => return false,
+        };
+    }
+    if end == 0 || end > data.len() {
+        return false;
+    }
+    return match RecordReader::new(data).last() {
+        Some(Err(error)) => error.reason != Reason::Truncated,
+        _ => true,
+    };
+}
+
+// The first host name requested by the ClientHello, whose handshake message may span records.
+fn server_name(hello: &[u8]) -> Option<String> {
+    for record in RecordReader::new(hello) {
+        for message in record.ok()?.messages {
+            if let Message::Handshake(handshake) = message {
+                return handshake.msg.and_then(|msg| msg.server_name().map(String::from));
+            }
+        }
+    }
+    return None;
+}
+
+// Host of an authority-form target without the port or IPv6 literal brackets.
+fn host(authority: &str) -> &str {
+    let host = match authority.rfind(':') {
+        Some(i) if !authority[i..].contains(']') => &authority[..i],
+        _ => authority,
+    };
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+// Origin servers are verified against the Mozilla root certificates. Only HTTP/1.1 is offered as
+// it is the only version the proxy can relay.
+pub fn client_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        let mut config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Arc::new(config)
+    }).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse::tls_request::ClientHelloBuilder;
+
+    #[test]
+    fn authority_host() {
+        assert_eq!(host("example.com:443"), "example.com");
+        assert_eq!(host("[::1]:443"), "::1");
+        assert_eq!(host("example.com"), "example.com");
+    }
+
+    #[test]
+    fn fragmented_hello() {
+        let record = ClientHelloBuilder::new().server_name("example.com").build().to_record().unwrap();
+        let handshake = &record[RECORD_HEADER_LENGTH..];
+        // The same ClientHello split across two handshake records.
+        let mut split = Vec::new();
+        for fragment in [&handshake[..10], &handshake[10..]] {
+            split.extend_from_slice(&[22, 3, 1]);
+            split.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+            split.extend_from_slice(fragment);
+        }
+        assert!(hello_complete(&record));
+        assert!(!hello_complete(&record[..record.len() - 1]));
+        assert!(!hello_complete(&split[..RECORD_HEADER_LENGTH + 10]));
+        assert!(!hello_complete(&split[..RECORD_HEADER_LENGTH + 12]));
+        assert!(hello_complete(&split));
+        assert!(hello_complete(b"GET"));
+        assert_eq!(server_name(&record).as_deref(), Some("example.com"));
+        assert_eq!(server_name(&split).as_deref(), Some("example.com"));
+    }
+}
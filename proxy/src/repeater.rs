@@ -0,0 +1,119 @@
+use std::io;
+use std::io::{Read, Write};
+
+use parse::flow::{Flow, Origin};
+use parse::http_reader::MessageReader;
+use parse::http_request::HTTPRequest;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConnection, StreamOwned};
+
+use crate::event::{ProxyError, ProxyEvent};
+use crate::http;
+use crate::mitm;
+use crate::{connect, Config, Reporter, report_error, timestamp};
+
+// Send the request to the origin server the captured request was relayed to, over TLS if it was
+// intercepted, and report the attempt once the response has been received. Attempts have the ID
+// of the captured flow and are not counted as flows served by the proxy.
+pub fn repeat(captured: &Flow, origin: &Origin, data: Vec<u8>, config: &Config, reporter: &Reporter) {
+    let mut attempt = Flow::new(captured.id, captured.connection, captured.client, parse::Request::new(timestamp(), data));
+    attempt.origin = Some(origin.clone());
+    if let Err(error) = send(&mut attempt, origin, config) {
+        report_error(reporter, error);
+    }
+    attempt.complete(timestamp());
+    match reporter.sender.send(ProxyEvent::Repeated(Box::new(attempt))) {
+        Ok(_) => {},
+        Err(_) => {},
+    };
+}
+
+fn send(attempt: &mut Flow, origin: &Origin, config: &Config) -> Result<(), ProxyError> {
+    let id = attempt.id;
+    let failed = |reason: String| ProxyError::Repeat { flow: id, reason: reason };
+    let data = attempt.request.data().to_vec();
+    let head_length = http::head_length(&data).unwrap_or(data.len());
+    let request = match HTTPRequest::new(&data[..head_length]) {
+        Ok(request) => request,
+        Err(error) => return Err(failed(error.to_string())),
+    };
+
+    let mut upstream = connect(&origin.authority, config)?;
+    attempt.connected(timestamp(), upstream.peer_addr().ok());
+    let server_name = match origin.server_name {
+        Some(ref server_name) => server_name,
+        None => return exchange(&mut upstream, &request, &data[head_length..], attempt).map_err(|error| failed(error.to_string())),
+    };
+
+    let tls_error = |reason: String| ProxyError::Tls { host: server_name.clone(), reason: reason };
+    let name = ServerName::try_from(server_name.clone()).map_err(|error| tls_error(error.to_string()))?;
+    let mut connection = ClientConnection::new(mitm::client_config(), name).map_err(|error| tls_error(error.to_string()))?;
+    connection.complete_io(&mut upstream).map_err(|error| tls_error(error.to_string()))?;
+    let mut upstream = StreamOwned::new(connection, upstream);
+    return exchange(&mut upstream, &request, &data[head_length..], attempt).map_err(|error| failed(error.to_string()));
+}
+
+// Write the request and read responses into the attempt until the final response.
+fn exchange<S: Read + Write>(upstream: &mut S, request: &HTTPRequest, body: &[u8], attempt: &mut Flow) -> io::Result<()> {
+    upstream.write_all(&http::rewrite(request, body))?;
+    upstream.flush()?;
+
+    let mut reader = MessageReader::response();
+    reader.set_request_method(request.method());
+    loop {
+        match http::read_message(upstream, &mut reader)? {
+            http::Message::Complete(response) => {
+                attempt.extend_response(timestamp(), &response);
+                if !http::is_interim(&response) {
+                    return Ok(());
+                }
+            },
+            http::Message::Malformed(response) => {
+                attempt.extend_response(timestamp(), &response);
+                return Ok(());
+            },
+            http::Message::Closed => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "closed without a response")),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+
+    use super::*;
+    use crate::hold::{HoldRules, Holds};
+    use crate::{Counters, Limits};
+
+    #[test]
+    fn repeat_plain_request() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let origin = Origin { authority: server.local_addr().unwrap().to_string(), server_name: None };
+        let server = thread::spawn(move || {
+            let (mut stream, _) = server.accept().unwrap();
+            let request = http::read_message(&mut stream, &mut MessageReader::request()).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK").unwrap();
+            return request;
+        });
+
+        let (sender, receiver) = mpsc::channel();
+        let reporter = Reporter { sender: sender, counters: Arc::new(Counters::default()) };
+        let config = Config { authority: None, limits: Limits::default(), upstream_proxy: None, holds: Holds::new(HoldRules::default()) };
+        let captured = Flow::new(7, 1, "127.0.0.1:1".parse().unwrap(), parse::Request::new(0, Vec::new()));
+        repeat(&captured, &origin, b"GET http://example.com/a HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec(), &config, &reporter);
+
+        match server.join().unwrap() {
+            http::Message::Complete(request) => assert_eq!(request, b"GET /a HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            _ => panic!("no request received"),
+        };
+        match receiver.recv().unwrap() {
+            ProxyEvent::Repeated(attempt) => {
+                assert_eq!(attempt.id, 7);
+                assert_eq!(attempt.response.unwrap().data(), b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK");
+            },
+            _ => panic!("attempt not reported"),
+        };
+    }
+}
@@ -0,0 +1,130 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use parse::flow::Flow;
+use parse::http_request::HTTPRequest;
+
+use crate::http;
+use crate::{connect, Config, Reporter, next_flow_id, report, report_error, timestamp};
+
+// RFC 9110 9.3.6 - Establish a tunnel to the authority-form target of a CONNECT request and relay
+// bytes in both directions.
+pub fn open(mut client: TcpStream, request: &HTTPRequest, early_data: &[u8], mut flow: Flow, config: &Config, reporter: &Reporter) {
+    let upstream = match connect(request.target(), config) {
+        Ok(upstream) => upstream,
+        Err(error) => {
+            report_error(reporter, error);
+            return http::respond(&mut client, http::BAD_GATEWAY, &mut flow, reporter);
+        },
+    };
+    flow.connected(timestamp(), upstream.peer_addr().ok());
+    http::respond(&mut client, http::CONNECTION_ESTABLISHED, &mut flow, reporter);
+
+    relay(client, upstream, early_data, flow.connection, reporter);
+}
+
+// Relay bytes between the peers of an established tunnel. Every chunk sent by the client starts a
+// new flow on the client connection and the chunks sent back by the server make up its response.
+// The tunnel is closed once neither peer has sent anything within the streams' read timeout.
+pub fn relay(client: TcpStream, mut upstream: TcpStream, early_data: &[u8], connection: u64, reporter: &Reporter) {
+    let (client_reader, upstream_reader) = match (client.try_clone(), upstream.try_clone()) {
+        (Ok(client_reader), Ok(upstream_reader)) => (client_reader, upstream_reader),
+        _ => return,
+    };
+    let tunnel = match (client.peer_addr(), upstream.peer_addr()) {
+        (Ok(client), Ok(upstream)) => Arc::new(Tunnel { connection: connection, client: client, upstream: upstream, flow: Mutex::new(None), activity: AtomicU64::new(timestamp() as u64) }),
+        _ => return,
+    };
+
+    // Bytes sent by an eager client before receiving the response belong to the tunnel.
+    if !early_data.is_empty() {
+        tunnel.request(early_data, reporter);
+        if upstream.write_all(early_data).is_err() {
+            return;
+        }
+    }
+
+    let downstream_tunnel = tunnel.clone();
+    let downstream_reporter = reporter.clone();
+    let downstream = thread::spawn(move || {
+        pipe(upstream_reader, client, &downstream_tunnel, |data| downstream_tunnel.response(data, &downstream_reporter))
+    });
+    pipe(client_reader, upstream, &tunnel, |data| tunnel.request(data, reporter));
+    let _ = downstream.join();
+}
+
+struct Tunnel {
+    connection: u64,
+    client: SocketAddr,
+    upstream: SocketAddr,
+    // The flow most recently started by the client.
+    flow: Mutex<Option<Flow>>,
+    // Timestamp of the last data sent in either direction.
+    activity: AtomicU64,
+}
+
+impl Tunnel {
+    fn active(&self) {
+        self.activity.store(timestamp() as u64, Ordering::Relaxed);
+    }
+
+    // A read timing out in one direction leaves the tunnel open while the other direction is busy.
+    fn idle(&self, timeout: Duration) -> bool {
+        let last = u128::from(self.activity.load(Ordering::Relaxed));
+        return timestamp().saturating_sub(last) >= timeout.as_millis();
+    }
+
+    fn request(&self, data: &[u8], reporter: &Reporter) {
+        let flow = self.start(data, reporter);
+        report(reporter, &flow);
+        *self.flow.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(flow);
+    }
+
+    fn response(&self, data: &[u8], reporter: &Reporter) {
+        let mut current = self.flow.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // A server speaking first responds to an empty request.
+        let flow = current.get_or_insert_with(|| self.start(&[], reporter));
+        flow.extend_response(timestamp(), data);
+        flow.complete(timestamp());
+        report(reporter, flow);
+    }
+
+    fn start(&self, data: &[u8], reporter: &Reporter) -> Flow {
+        let mut flow = Flow::new(next_flow_id(reporter), self.connection, self.client, parse::Request::new(timestamp(), data.to_vec()));
+        flow.connected(timestamp(), Some(self.upstream));
+        return flow;
+    }
+}
+
+// Copy bytes from one peer to the other until either side closes or the tunnel is idle, then
+// half-close the receiver so it observes the end of stream.
+fn pipe<F: Fn(&[u8])>(mut from: TcpStream, mut to: TcpStream, tunnel: &Tunnel, capture: F) {
+    let timeout = from.read_timeout().ok().flatten();
+    let mut buf = [0u8; 16384];
+    loop {
+        match from.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                tunnel.active();
+                capture(&buf[..n]);
+                if to.write_all(&buf[..n]).is_err() {
+                    break;
+                }
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+                match timeout {
+                    Some(timeout) if !tunnel.idle(timeout) => continue,
+                    _ => break,
+                };
+            },
+            Err(_) => break,
+        };
+    }
+    let _ = to.shutdown(Shutdown::Write);
+}
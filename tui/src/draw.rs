@@ -1,11 +1,22 @@
 use core::fmt::{Display};
 use crate::Encoding;
+use crate::editor::Editor;
 
 pub trait LogEntry {
     fn timestamp(&self) -> String;
     fn to_lines(&self, encoding: &Encoding) -> Vec<String>;
 }
 
+impl<T: LogEntry> LogEntry for &T {
+    fn timestamp(&self) -> String {
+        (*self).timestamp()
+    }
+
+    fn to_lines(&self, encoding: &Encoding) -> Vec<String> {
+        (*self).to_lines(encoding)
+    }
+}
+
 pub fn line_numbers(origin: (usize, usize), rows: usize) {
     let rows = rows - origin.0;
     for i in 0..rows {
@@ -22,7 +33,7 @@ pub fn fill(origin: (usize, usize), rows: usize, cols: usize, background: Option
     }
 }
 
-pub fn list<T: Display>(origin: (usize, usize), data: &Vec<T>) {
+pub fn list<T: Display>(origin: (usize, usize), data: &[T]) {
     for (i, datum) in data.iter().enumerate() {
         print!("[{};{}H[1m[38;2;{};{};{};48;2;{};{};{}m{}[0m",
             origin.0 + i,
@@ -32,20 +43,73 @@ pub fn list<T: Display>(origin: (usize, usize), data: &Vec<T>) {
     }
 }
 
-pub fn log<T: LogEntry>(origin: (usize, usize), entries: &Vec<T>, encoding: &Encoding) {
+// Draw the entries in rows from the origin. The log scrolls to keep the selected entry, shown in
+// reverse colours, in view.
+pub fn log<T: LogEntry>(origin: (usize, usize), rows: usize, entries: &[T], encoding: &Encoding, selected: Option<usize>) {
     let (row, col) = origin;
+    let entries: Vec<Vec<String>> = entries.iter().map(|entry| entry.to_lines(encoding)).collect();
+    let selected = selected.filter(|selected| *selected < entries.len());
+
+    // Show as many entries as fit before the selected entry.
+    let mut first = 0;
+    if let Some(selected) = selected {
+        first = selected;
+        let mut used = entries[selected].len();
+        while first > 0 && used + entries[first - 1].len() <= rows {
+            first -= 1;
+            used += entries[first].len();
+        }
+    }
 
     let mut written_lines = 0;
-    for entry in entries {
-        let lines = entry.to_lines(encoding);
-        for (j, line) in lines.iter().enumerate() {
+    for (i, lines) in entries.iter().enumerate().skip(first) {
+        let colours = if selected == Some(i) { (0xcb, 0xc9, 0xe2, 0x54, 0x27, 0x8f) } else { (0x54, 0x27, 0x8f, 0xcb, 0xc9, 0xe2) };
+        for line in lines.iter() {
+            if written_lines >= rows {
+                return;
+            }
             print!("[{};{}H[1m[38;2;{};{};{};48;2;{};{};{}m{}[0m",
-                row + written_lines + j,
+                row + written_lines,
                 col,
-                0x54, 0x27, 0x8f, 0xcb, 0xc9, 0xe2,
+                colours.0, colours.1, colours.2, colours.3, colours.4, colours.5,
                 line);
+            written_lines += 1;
         }
-        written_lines += lines.len();
+    }
+}
+
+// Draw the editor's text with the cursor in reverse video, scrolled to keep the cursor in view.
+// Control characters are shown as dots so they cannot disturb the terminal. Every row is padded
+// to the width, so deleted text and lines are overwritten.
+pub fn editor(origin: (usize, usize), rows: usize, cols: usize, editor: &Editor) {
+    let (cursor_row, cursor_col) = editor.cursor();
+    let top = (cursor_row + 1).saturating_sub(rows);
+    let left = (cursor_col + 1).saturating_sub(cols);
+    let lines = editor.lines();
+    for i in top..top + rows {
+        let mut text = String::new();
+        let mut width = 0;
+        if let Some(line) = lines.get(i) {
+            for (j, c) in line.iter().enumerate().skip(left).take(cols) {
+                let c = if c.is_control() { '\u{b7}' } else { *c };
+                if i == cursor_row && j == cursor_col {
+                    text.push_str(&format!("[7m{}[27m", c));
+                } else {
+                    text.push(c);
+                }
+                width += 1;
+            }
+            if i == cursor_row && cursor_col >= line.len() {
+                text.push_str("[7m [27m");
+                width += 1;
+            }
+        }
+        print!("[{};{}H[38;2;{};{};{};48;2;{};{};{}m{}{}[0m",
+            origin.0 + i - top,
+            origin.1,
+            0x54, 0x27, 0x8f, 0xcb, 0xc9, 0xe2,
+            text,
+            " ".repeat(cols.saturating_sub(width)));
     }
 }
 
@@ -54,6 +118,33 @@ pub fn status_line(rows: usize, cols: usize) {
     print!("[{};1H[30;47m{} x {}[0m", rows, rows, cols);
 }
 
+// Draw the lines from the top line on, padded to fill every row so earlier text is overwritten.
+pub fn page(origin: (usize, usize), rows: usize, cols: usize, lines: &[String], top: usize) {
+    for i in 0..rows {
+        let line: String = lines.get(top + i).map(|line| line.chars().take(cols).collect()).unwrap_or_default();
+        let padding = " ".repeat(cols.saturating_sub(line.chars().count()));
+        print!("[{};{}H[38;2;{};{};{};48;2;{};{};{}m{}{}[0m",
+            origin.0 + i,
+            origin.1,
+            0x54, 0x27, 0x8f, 0xcb, 0xc9, 0xe2,
+            line,
+            padding);
+    }
+}
+
+// Fill the row with the title, in the colours of the status line.
+pub fn title_line(row: usize, cols: usize, title: &str) {
+    let title: String = title.chars().take(cols).collect();
+    print!("[{};1H[30;47m{}{}[0m", row, title, " ".repeat(cols.saturating_sub(title.chars().count())));
+}
+
+// Right-align the message in the status line, truncating it to fit.
+pub fn status_message(rows: usize, cols: usize, message: &str) {
+    let message: String = message.chars().take(cols.saturating_sub(12)).collect();
+    let col = cols.saturating_sub(message.chars().count()) + 1;
+    print!("[{};{}H[30;47m{}[0m", rows, col, message);
+}
+
 pub enum Colour {
     TrueColour { red: u32, green: u32, blue: u32 },
 }
@@ -83,7 +174,7 @@ impl Style {
 
         // Add background colour style if it was specified
         if let Some(colour) = self.background {
-            if style != "" {
+            if !style.is_empty() {
                 style.push(';');
             }
             match colour {
@@ -97,7 +188,7 @@ impl Style {
     fn style_string(self, s: &str) -> String {
         let style = self.get_style();
 
-        if style != "" {
+        if !style.is_empty() {
             format!("[{}m{}[0m", style, s)
         } else {
             s.to_string()
@@ -109,8 +200,9 @@ impl Style {
 mod tests {
     use super::*;
 
+    #[test]
     fn coloured_style() {
         let style = Style::new(Some(Colour::TrueColour { red: 84, green: 39, blue: 143 }), Some(Colour::TrueColour { red: 203, green: 201, blue: 226 }));
-        assert_eq!(style.get_style(), "38;2;84;39;143;48;2;203;201;226m")
+        assert_eq!(style.get_style(), "38;2;84;39;143;48;2;203;201;226")
     }
 }
@@ -0,0 +1,144 @@
+use crate::input::Key;
+
+// A multi-line text buffer edited at a cursor.
+pub struct Editor {
+    lines: Vec<Vec<char>>,
+    row: usize,
+    col: usize,
+}
+
+impl Editor {
+    pub fn new(text: &str) -> Self {
+        Editor {
+            lines: text.split('\n').map(|line| line.chars().collect()).collect(),
+            row: 0,
+            col: 0,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(|line| line.iter().collect()).collect();
+        lines.join("\n")
+    }
+
+    pub fn lines(&self) -> &[Vec<char>] {
+        &self.lines
+    }
+
+    // Line and column of the cursor, counted in characters from zero.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    // Apply an editing or cursor movement key. Returns false for keys without an effect.
+    pub fn handle(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char(c) => self.insert(c),
+            Key::Enter => self.newline(),
+            Key::Backspace => self.backspace(),
+            Key::Delete => self.delete(),
+            Key::Up => self.up(),
+            Key::Down => self.down(),
+            Key::Left => self.left(),
+            Key::Right => self.right(),
+            Key::Home => self.col = 0,
+            Key::End => self.col = self.lines[self.row].len(),
+            Key::Ctrl(_) => return false,
+        };
+        return true;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.lines[self.row].insert(self.col, c);
+        self.col += 1;
+    }
+
+    // Split the line at the cursor, moving the cursor to the start of the new line.
+    pub fn newline(&mut self) {
+        let rest = self.lines[self.row].split_off(self.col);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+    }
+
+    // Remove the character before the cursor, joining the line to the previous one at its start.
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            self.lines[self.row].remove(self.col);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+            self.lines[self.row].extend(line);
+        }
+    }
+
+    // Remove the character at the cursor, joining the next line to the line at its end.
+    pub fn delete(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.lines[self.row].remove(self.col);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].extend(line);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    // Moving between lines keeps the column where possible.
+    pub fn up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.lines[self.row].len());
+        }
+    }
+
+    pub fn down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.lines[self.row].len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_lines() {
+        let mut editor = Editor::new("GET / HTTP/1.1\nHost: a");
+        editor.handle(Key::Down);
+        editor.handle(Key::End);
+        editor.handle(Key::Char('b'));
+        editor.handle(Key::Enter);
+        for c in "X: 1".chars() {
+            editor.handle(Key::Char(c));
+        }
+        assert_eq!(editor.text(), "GET / HTTP/1.1\nHost: ab\nX: 1");
+        assert_eq!(editor.cursor(), (2, 4));
+
+        editor.handle(Key::Home);
+        editor.handle(Key::Backspace);
+        editor.handle(Key::Delete);
+        assert_eq!(editor.text(), "GET / HTTP/1.1\nHost: ab: 1");
+        assert_eq!(editor.cursor(), (1, 8));
+    }
+}
@@ -0,0 +1,116 @@
+// A key pressed at the terminal.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Key {
+    Char(char),
+    // A letter pressed with the control key, given in lower case.
+    Ctrl(char),
+    Enter,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+}
+
+// Decodes the bytes read from a terminal in raw mode into keys. Escape sequences and multi-byte
+// UTF-8 characters arrive one byte at a time, so bytes are buffered until a key is complete.
+pub struct KeyDecoder {
+    pending: Vec<u8>,
+}
+
+impl Default for KeyDecoder {
+    fn default() -> Self {
+        KeyDecoder::new()
+    }
+}
+
+impl KeyDecoder {
+    pub fn new() -> Self {
+        KeyDecoder {
+            pending: Vec::new(),
+        }
+    }
+
+    // Returns the key completed by the byte, if any.
+    pub fn push(&mut self, byte: u8) -> Option<Key> {
+        self.pending.push(byte);
+        match decode(&self.pending) {
+            Decoded::Key(key) => {
+                self.pending.clear();
+                return Some(key);
+            },
+            Decoded::Incomplete => return None,
+            Decoded::Unknown => {
+                self.pending.clear();
+                return None;
+            },
+        }
+    }
+}
+
+enum Decoded {
+    Key(Key),
+    Incomplete,
+    // Sequences for keys without a use, such as function keys, are discarded.
+    Unknown,
+}
+
+fn decode(bytes: &[u8]) -> Decoded {
+    match bytes {
+        [b'\r'] | [b'\n'] => Decoded::Key(Key::Enter),
+        [0x7f] | [0x08] => Decoded::Key(Key::Backspace),
+        [b'\t'] => Decoded::Key(Key::Char('\t')),
+        [byte @ 0x01..=0x1a] => Decoded::Key(Key::Ctrl(char::from(byte + b'a' - 1))),
+        // ECMA-48 - Control sequences start with ESC [ and end with a final byte from @ to ~.
+        [0x1b] | [0x1b, b'['] | [0x1b, b'O'] => Decoded::Incomplete,
+        [0x1b, b'[' | b'O', rest @ ..] => {
+            let last = rest[rest.len() - 1];
+            if !(0x40..=0x7e).contains(&last) {
+                return Decoded::Incomplete;
+            }
+            match rest {
+                [b'A'] => Decoded::Key(Key::Up),
+                [b'B'] => Decoded::Key(Key::Down),
+                [b'C'] => Decoded::Key(Key::Right),
+                [b'D'] => Decoded::Key(Key::Left),
+                [b'H'] | [b'1', b'~'] | [b'7', b'~'] => Decoded::Key(Key::Home),
+                [b'F'] | [b'4', b'~'] | [b'8', b'~'] => Decoded::Key(Key::End),
+                [b'3', b'~'] => Decoded::Key(Key::Delete),
+                _ => Decoded::Unknown,
+            }
+        },
+        [0x1b, ..] => Decoded::Unknown,
+        _ => match std::str::from_utf8(bytes) {
+            Ok(text) => match text.chars().next() {
+                Some(c) => Decoded::Key(Key::Char(c)),
+                None => Decoded::Unknown,
+            },
+            // UTF-8 encodes characters in at most four bytes.
+            Err(e) if e.error_len().is_none() && bytes.len() < 4 => Decoded::Incomplete,
+            Err(_) => Decoded::Unknown,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(bytes: &[u8]) -> Vec<Key> {
+        let mut decoder = KeyDecoder::new();
+        bytes.iter().filter_map(|byte| decoder.push(*byte)).collect()
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(decode_all(b"\x1b[A\x1b[3~q\x1b[15~\x13"), vec![Key::Up, Key::Delete, Key::Char('q'), Key::Ctrl('s')]);
+    }
+
+    #[test]
+    fn utf8_characters() {
+        assert_eq!(decode_all("é€\r".as_bytes()), vec![Key::Char('é'), Key::Char('€'), Key::Enter]);
+    }
+}
@@ -2,9 +2,13 @@ use core::fmt::{Display};
 use std::io;
 use std::io::Write;
 
-use libc;
 
 pub mod draw;
+pub mod editor;
+pub mod input;
+
+use crate::editor::Editor;
+use crate::input::Key;
 
 pub enum Encoding {
     Hex,
@@ -12,16 +16,26 @@ pub enum Encoding {
     Protocol(String),
 }
 
-pub struct UI<T: Display, F: Fn(usize, usize, &Vec<T>, &Encoding)> {
+pub struct UI<T: Display, F: Fn(usize, usize, &[&T], &Encoding, Option<usize>)> {
     initial_config: libc::termios,
     rows: usize,
     cols: usize,
     model: Vec<T>,
     render: F,
     encoding: Encoding,
+    // Message shown in the status line, such as the last error reported.
+    status: String,
+    // Index of the datum selected in the log.
+    selected: Option<usize>,
+    // Text the data shown in the log must contain when displayed. Empty shows every datum.
+    filter: String,
+    // Text being edited full-screen under a title, drawn in place of the log.
+    editor: Option<(String, Editor)>,
+    // Lines shown full-screen under a title, and the first line shown.
+    page: Option<(String, Vec<String>, usize)>,
 }
 
-impl<T: Display, F: Fn(usize, usize, &Vec<T>, &Encoding)> UI<T, F> {
+impl<T: Display, F: Fn(usize, usize, &[&T], &Encoding, Option<usize>)> UI<T, F> {
     pub fn new(model: Vec<T>, render: F) -> Self {
         let (rows, cols) = Self::terminal_size();
 
@@ -44,6 +58,11 @@ impl<T: Display, F: Fn(usize, usize, &Vec<T>, &Encoding)> UI<T, F> {
             model: model,
             render: render,
             encoding: Encoding::Hex,
+            status: String::new(),
+            selected: None,
+            filter: String::new(),
+            editor: None,
+            page: None,
         }
     }
 
@@ -59,9 +78,20 @@ impl<T: Display, F: Fn(usize, usize, &Vec<T>, &Encoding)> UI<T, F> {
         self.render();
     }
 
+    // Replace the first datum matching the predicate, or add the datum if none match.
+    pub fn update_data<P: Fn(&T) -> bool>(&mut self, datum: T, matches: P) {
+        match self.model.iter().position(matches) {
+            Some(i) => self.model[i] = datum,
+            None => self.model.push(datum),
+        };
+        self.render();
+    }
+
     pub fn start(&self) {
         let mut config = self.initial_config;
         config.c_lflag = config.c_lflag & !libc::ECHO & !libc::ICANON;
+        // Let Ctrl-S and Ctrl-Q through instead of pausing output.
+        config.c_iflag &= !libc::IXON;
         unsafe { libc::tcsetattr(1, libc::TCSANOW, &config) };
 
         // Switch to alternate buffer
@@ -91,8 +121,133 @@ impl<T: Display, F: Fn(usize, usize, &Vec<T>, &Encoding)> UI<T, F> {
         self.render();
     }
 
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+        self.render();
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.model
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.selected.and_then(|i| self.model.get(i))
+    }
+
+    // Select the next datum shown, starting from the first.
+    pub fn select_next(&mut self) {
+        let next = match self.selected {
+            Some(i) => (i + 1..self.model.len()).find(|j| self.shows(&self.model[*j])),
+            None => (0..self.model.len()).find(|j| self.shows(&self.model[*j])),
+        };
+        if next.is_some() {
+            self.selected = next;
+        }
+        self.render();
+    }
+
+    // Select the previous datum shown, starting from the last.
+    pub fn select_previous(&mut self) {
+        let previous = match self.selected {
+            Some(i) => (0..i).rev().find(|j| self.shows(&self.model[*j])),
+            None => (0..self.model.len()).rev().find(|j| self.shows(&self.model[*j])),
+        };
+        if previous.is_some() {
+            self.selected = previous;
+        }
+        self.render();
+    }
+
+    // Show only the data containing the text when displayed. The selection is cleared if hidden.
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        if self.selected().is_some_and(|datum| !self.shows(datum)) {
+            self.selected = None;
+        }
+        print!("[2J");
+        self.render();
+    }
+
+    fn shows(&self, datum: &T) -> bool {
+        self.filter.is_empty() || datum.to_string().contains(&self.filter)
+    }
+
+    // Edit the text full-screen until the editor is closed.
+    pub fn open_editor(&mut self, title: String, text: &str) {
+        self.editor = Some((title, Editor::new(text)));
+        print!("[2J");
+        self.render();
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editor.is_some()
+    }
+
+    // Pass the key to the open editor. Returns false if no editor is open or the key has no effect.
+    pub fn edit(&mut self, key: Key) -> bool {
+        let handled = match self.editor.as_mut() {
+            Some((_, editor)) => editor.handle(key),
+            None => false,
+        };
+        if handled {
+            self.render();
+        }
+        return handled;
+    }
+
+    // Close the editor, returning the edited text.
+    pub fn close_editor(&mut self) -> Option<String> {
+        let (_, editor) = self.editor.take()?;
+        print!("[2J");
+        self.render();
+        return Some(editor.text());
+    }
+
+    // Show the lines full-screen until the page is closed. Drawn beneath an open editor.
+    pub fn open_page(&mut self, title: String, lines: Vec<String>) {
+        self.page = Some((title, lines, 0));
+        self.render();
+    }
+
+    pub fn is_paging(&self) -> bool {
+        self.page.is_some()
+    }
+
+    // Move the page by the number of lines, up if negative.
+    pub fn scroll_page(&mut self, lines: isize) {
+        if let Some((_, ref page, ref mut top)) = self.page {
+            *top = top.saturating_add_signed(lines).min(page.len().saturating_sub(1));
+        }
+        self.render();
+    }
+
+    pub fn close_page(&mut self) {
+        self.page = None;
+        self.render();
+    }
+
     fn render(&self) {
-        (self.render)(self.rows, self.cols, &self.model, &self.encoding);
+        match (&self.editor, &self.page) {
+            (Some((title, editor)), _) => {
+                draw::title_line(1, self.cols, title);
+                draw::status_line(self.rows, self.cols);
+                draw::editor((2, 1), self.rows.saturating_sub(2), self.cols, editor);
+            },
+            (None, Some((title, lines, top))) => {
+                draw::title_line(1, self.cols, title);
+                draw::page((2, 1), self.rows.saturating_sub(2), self.cols, lines, *top);
+                draw::status_line(self.rows, self.cols);
+            },
+            (None, None) => {
+                let shown: Vec<&T> = self.model.iter().filter(|datum| self.shows(datum)).collect();
+                // Position of the selected datum among those shown.
+                let selected = self.selected.map(|i| self.model[..i].iter().filter(|datum| self.shows(datum)).count());
+                (self.render)(self.rows, self.cols, &shown, &self.encoding, selected);
+            },
+        };
+        if !self.status.is_empty() {
+            draw::status_message(self.rows, self.cols, &self.status);
+        }
 
         io::stdout().flush().unwrap();
     }